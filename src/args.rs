@@ -0,0 +1,324 @@
+use crate::models::GameType;
+use crate::models::NetConditionerConfig;
+use crate::models::NetStatsDump;
+use log::LevelFilter;
+use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+const DEFAULT_PORT: u16 = 2003;
+const DEFAULT_TICK_RATE: u32 = 60;
+
+pub struct Args {
+    /// Skips the menus and hosts or joins a game right after startup
+    pub game_type: Option<GameType>,
+    pub is_headless: bool,
+    /// Lets a dedicated server answer discovery requests from the local network
+    pub is_discoverable: bool,
+    pub tick_rate: u32,
+    pub log_level: LevelFilter,
+    /// Overrides the `config` directory next to the executable
+    pub config_dir: Option<PathBuf>,
+    /// Overrides the player name from the config
+    pub player_name: Option<String>,
+    /// Joins the game without an actor just to watch it
+    pub is_spectator: bool,
+    /// Overrides the network conditioner from the config
+    pub net_conditioner: Option<NetConditionerConfig>,
+    /// Overrides where network statistics are dumped to
+    pub net_stats_dump: Option<NetStatsDump>,
+    /// Overrides the file to record network messages to
+    pub net_record: Option<PathBuf>,
+    /// Plays a recorded client session back right after startup
+    pub replay: Option<PathBuf>,
+}
+
+impl Args {
+    pub fn parse() -> Result<Self, String> {
+        return Self::parse_from(env::args().skip(1));
+    }
+
+    fn parse_from<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut parsed = Self::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--host" => {
+                    parsed.game_type = Some(GameType::Server(parse_value(&arg, args.next())?));
+                }
+                "--join" => {
+                    parsed.game_type = Some(GameType::Client(parse_value(&arg, args.next())?));
+                }
+                "--headless" => {
+                    parsed.is_headless = true;
+                }
+                "--discoverable" => {
+                    parsed.is_discoverable = true;
+                }
+                "--tick-rate" => {
+                    parsed.tick_rate = parse_value(&arg, args.next())?;
+                }
+                "--log-level" => {
+                    parsed.log_level = parse_value(&arg, args.next())?;
+                }
+                "--config-dir" => {
+                    parsed.config_dir = Some(parse_value(&arg, args.next())?);
+                }
+                "--spectate" => {
+                    parsed.is_spectator = true;
+                }
+                "--name" => {
+                    parsed.player_name = Some(parse_value(&arg, args.next())?);
+                }
+                "--net-loss" => {
+                    parsed.get_net_conditioner().loss = parse_chance(&arg, args.next())?;
+                }
+                "--net-latency" => {
+                    parsed.get_net_conditioner().latency =
+                        Duration::from_millis(parse_value(&arg, args.next())?);
+                }
+                "--net-jitter" => {
+                    parsed.get_net_conditioner().jitter =
+                        Duration::from_millis(parse_value(&arg, args.next())?);
+                }
+                "--net-duplication" => {
+                    parsed.get_net_conditioner().duplication = parse_chance(&arg, args.next())?;
+                }
+                "--net-reordering" => {
+                    parsed.get_net_conditioner().reordering = parse_chance(&arg, args.next())?;
+                }
+                "--net-seed" => {
+                    parsed.get_net_conditioner().seed = parse_value(&arg, args.next())?;
+                }
+                "--net-stats" => {
+                    parsed.net_stats_dump = Some(parse_stats_dump(&arg, args.next())?);
+                }
+                "--record" => {
+                    parsed.net_record = Some(parse_value(&arg, args.next())?);
+                }
+                "--replay" => {
+                    parsed.replay = Some(parse_value(&arg, args.next())?);
+                }
+                _ => {
+                    return Err(format!("Unknown argument: {}", arg));
+                }
+            }
+        }
+
+        if parsed.tick_rate == 0 {
+            return Err("Tick rate should be greater than zero".to_string());
+        }
+
+        if parsed.replay.is_some() && (parsed.game_type.is_some() || parsed.is_headless) {
+            return Err("Replay can't be combined with hosting or joining a game".to_string());
+        }
+
+        if parsed.is_spectator
+            && (parsed.is_headless || matches!(parsed.game_type, Some(GameType::Server(..))))
+        {
+            return Err("Only joining clients can spectate".to_string());
+        }
+
+        if parsed.is_headless {
+            match parsed.game_type {
+                Some(GameType::Server(..)) => {}
+                Some(GameType::Client(..)) => {
+                    return Err("Headless mode can only host a game".to_string());
+                }
+                None => {
+                    parsed.game_type = Some(GameType::Server(DEFAULT_PORT));
+                }
+            }
+        }
+
+        return Ok(parsed);
+    }
+
+    fn get_net_conditioner(&mut self) -> &mut NetConditionerConfig {
+        return self
+            .net_conditioner
+            .get_or_insert_with(NetConditionerConfig::default);
+    }
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        return Self {
+            game_type: None,
+            is_headless: false,
+            is_discoverable: false,
+            tick_rate: DEFAULT_TICK_RATE,
+            log_level: LevelFilter::Info,
+            config_dir: None,
+            player_name: None,
+            is_spectator: false,
+            net_conditioner: None,
+            net_stats_dump: None,
+            net_record: None,
+            replay: None,
+        };
+    }
+}
+
+fn parse_value<T: FromStr>(name: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("No value specified for {}", name))?;
+
+    return value
+        .parse()
+        .map_err(|_| format!("Wrong value for {}: {}", name, value));
+}
+
+fn parse_chance(name: &str, value: Option<String>) -> Result<f64, String> {
+    let chance: f64 = parse_value(name, value)?;
+
+    if (0.0..=1.0).contains(&chance) {
+        return Ok(chance);
+    } else {
+        return Err(format!("{} should be from 0 to 1", name));
+    }
+}
+
+fn parse_stats_dump(name: &str, value: Option<String>) -> Result<NetStatsDump, String> {
+    let value: String = parse_value(name, value)?;
+
+    if value == "log" {
+        return Ok(NetStatsDump::Log);
+    } else {
+        return Ok(NetStatsDump::Csv(PathBuf::from(value)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        return Args::parse_from(args.iter().map(|a| (*a).to_string()));
+    }
+
+    #[test]
+    fn test_parse_game_type() {
+        assert!(matches!(parse(&[]).map(|a| a.game_type), Ok(None)));
+
+        assert!(matches!(
+            parse(&["--host", "2004"]).map(|a| a.game_type),
+            Ok(Some(GameType::Server(2004))),
+        ));
+
+        assert!(matches!(
+            parse(&["--join", "127.0.0.1:2004"]).map(|a| a.game_type),
+            Ok(Some(GameType::Client(address))) if address.port() == 2004,
+        ));
+
+        assert!(parse(&["--host"]).is_err(), "No port");
+        assert!(parse(&["--host", "port"]).is_err(), "Wrong port");
+        assert!(
+            parse(&["--join", "127.0.0.1"]).is_err(),
+            "No port in address"
+        );
+    }
+
+    #[test]
+    fn test_parse_headless() {
+        assert!(matches!(
+            parse(&["--headless"]).map(|a| a.game_type),
+            Ok(Some(GameType::Server(DEFAULT_PORT))),
+        ));
+
+        assert!(matches!(
+            parse(&["--headless", "--discoverable"]).map(|a| a.is_discoverable),
+            Ok(true),
+        ));
+
+        assert!(parse(&["--headless", "--join", "127.0.0.1:2004"]).is_err());
+        assert!(parse(&["--headless", "--tick-rate", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_spectate() {
+        assert!(matches!(
+            parse(&["--spectate", "--join", "127.0.0.1:2004"]).map(|a| a.is_spectator),
+            Ok(true),
+        ));
+
+        assert!(parse(&["--spectate", "--host", "2004"]).is_err());
+        assert!(parse(&["--spectate", "--headless"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_other() {
+        let args = parse(&[
+            "--log-level",
+            "debug",
+            "--config-dir",
+            "/tmp/shooter",
+            "--name",
+            "Petrukha",
+        ]);
+        assert!(matches!(
+            args.as_ref().map(|a| a.log_level),
+            Ok(LevelFilter::Debug)
+        ));
+
+        assert_eq!(
+            Some("Petrukha"),
+            args.as_ref().ok().and_then(|a| a.player_name.as_deref()),
+        );
+
+        assert_eq!(
+            Some(PathBuf::from("/tmp/shooter")),
+            args.ok().and_then(|a| a.config_dir),
+        );
+
+        assert!(parse(&["--unknown"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_net_conditioner() {
+        assert!(matches!(parse(&[]).map(|a| a.net_conditioner), Ok(None)));
+
+        let conditioner = parse(&["--net-loss", "0.1", "--net-latency", "100"])
+            .ok()
+            .and_then(|a| a.net_conditioner);
+
+        assert_eq!(
+            Some(Duration::from_millis(100)),
+            conditioner.map(|c| c.latency)
+        );
+
+        assert_eq!(
+            Some(Duration::from_millis(0)),
+            conditioner.map(|c| c.jitter),
+            "Default",
+        );
+
+        assert!(parse(&["--net-loss", "2"]).is_err(), "Not a chance");
+    }
+
+    #[test]
+    fn test_parse_net_stats() {
+        assert!(matches!(
+            parse(&["--net-stats", "log"]).map(|a| a.net_stats_dump),
+            Ok(Some(NetStatsDump::Log)),
+        ));
+
+        assert!(matches!(
+            parse(&["--net-stats", "stats.csv"]).map(|a| a.net_stats_dump),
+            Ok(Some(NetStatsDump::Csv(ref path))) if path == &PathBuf::from("stats.csv"),
+        ));
+
+        assert!(parse(&["--net-stats"]).is_err(), "No target");
+    }
+
+    #[test]
+    fn test_parse_replay() {
+        assert_eq!(
+            Some(PathBuf::from("game.rec")),
+            parse(&["--replay", "game.rec"]).ok().and_then(|a| a.replay),
+        );
+
+        assert!(parse(&["--replay", "game.rec", "--host", "2004"]).is_err());
+        assert!(parse(&["--replay", "game.rec", "--headless"]).is_err());
+    }
+}
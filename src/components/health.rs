@@ -43,6 +43,10 @@ impl Health {
         }
     }
 
+    pub const fn get(&self) -> f32 {
+        return self.value;
+    }
+
     pub fn is_alive(&self) -> bool {
         return self.value > 0.0;
     }
@@ -7,6 +7,7 @@ mod own;
 mod player;
 mod projectile;
 mod rigid_body;
+mod spectator;
 mod terrain;
 mod weapon;
 
@@ -19,5 +20,6 @@ pub use self::own::*;
 pub use self::player::*;
 pub use self::projectile::*;
 pub use self::rigid_body::*;
+pub use self::spectator::*;
 pub use self::terrain::*;
 pub use self::weapon::*;
@@ -13,6 +13,8 @@ pub struct Projectile {
     pub initial_position: Vector2<f32>,
     pub initial_velocity: Vector2<f32>,
     pub shooter: Option<Entity>,
+    /// How far back in time obstacles are checked, to match what the shooter saw
+    pub rewind: Duration,
 }
 
 #[derive(Clone)]
@@ -31,6 +33,7 @@ impl Projectile {
         position: Vector2<f32>,
         velocity: Vector2<f32>,
         shooter: Option<Entity>,
+        rewind: Duration,
     ) -> Self {
         return Self {
             config,
@@ -38,6 +41,7 @@ impl Projectile {
             initial_position: position,
             initial_velocity: velocity,
             shooter,
+            rewind,
         };
     }
 
@@ -50,8 +54,50 @@ impl Projectile {
 
         return (p + (v_1 - v_0) / a * VISUAL_VELOCITY_FACTOR, v_1);
     }
+
+    /// How far a projectile flies until it stops. Endless if it doesn't slow down
+    pub fn calc_range(velocity: f32, acceleration_factor: f32) -> f32 {
+        if acceleration_factor < 0.0 {
+            return (velocity - Self::VELOCITY_MIN).max(0.0) / -acceleration_factor
+                * VISUAL_VELOCITY_FACTOR;
+        } else {
+            return f32::INFINITY;
+        }
+    }
 }
 
 impl Component for Projectile {
     type Storage = DenseVecStorage<Self>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_calc_range() {
+        let (velocity, acceleration_factor) = (320.0, -7.0);
+        let stop_time = (Projectile::VELOCITY_MIN / velocity).ln() / acceleration_factor;
+        let projectile = Projectile::new(
+            ProjectileConfig {
+                acceleration_factor,
+            },
+            Duration::from_secs(0),
+            Vector2::new(1.0, 2.0),
+            Vector2::new(0.0, velocity),
+            None,
+            Duration::from_secs(0),
+        );
+
+        let (position, _) = projectile.calc_data(Duration::from_secs_f32(stop_time));
+
+        assert_abs_diff_eq!(
+            position.y - 2.0,
+            Projectile::calc_range(velocity, acceleration_factor),
+            epsilon = 0.01,
+        );
+
+        assert!(Projectile::calc_range(velocity, 0.0).is_infinite());
+    }
+}
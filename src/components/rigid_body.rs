@@ -2,6 +2,7 @@ use amethyst::core::math::Vector2;
 use amethyst::ecs::Component;
 use amethyst::ecs::DenseVecStorage;
 
+#[derive(Clone)]
 pub struct RigidBody {
     pub mass: f32,
     pub drag: f32,
@@ -0,0 +1,61 @@
+use amethyst::ecs::Component;
+use amethyst::ecs::Entity;
+use amethyst::ecs::HashMapStorage;
+
+/// Carries the camera of a client without an actor. Follows the target if any or moves freely
+pub struct Spectator {
+    pub target: Option<Entity>,
+}
+
+impl Spectator {
+    pub const fn new() -> Self {
+        return Self { target: None };
+    }
+
+    /// Follows the next of the candidates, or moves freely after the last one
+    pub fn cycle_target(&mut self, candidates: &[Entity]) {
+        let next = match self.target {
+            Some(target) => candidates
+                .iter()
+                .position(|c| *c == target)
+                .and_then(|i| candidates.get(i + 1)),
+            None => candidates.first(),
+        };
+
+        self.target = next.copied();
+    }
+}
+
+impl Component for Spectator {
+    type Storage = HashMapStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst::ecs::Builder;
+    use amethyst::ecs::World;
+    use amethyst::ecs::WorldExt;
+
+    #[test]
+    fn test_cycle_target() {
+        let mut world = World::new();
+        let a = world.create_entity().build();
+        let b = world.create_entity().build();
+        let gone = world.create_entity().build();
+        let mut spectator = Spectator::new();
+
+        spectator.cycle_target(&[a, b]);
+        assert_eq!(Some(a), spectator.target);
+
+        spectator.cycle_target(&[a, b]);
+        assert_eq!(Some(b), spectator.target);
+
+        spectator.cycle_target(&[a, b]);
+        assert_eq!(None, spectator.target, "Free after the last one");
+
+        spectator.target = Some(gone);
+        spectator.cycle_target(&[a, b]);
+        assert_eq!(None, spectator.target, "Free if the target is gone");
+    }
+}
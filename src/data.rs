@@ -1,5 +1,11 @@
 use std::time::Duration;
 
+/// Short hash of the commit the game was built from
+pub const BUILD_HASH: &str = env!("BUILD_HASH");
+
+/// The only map so far
+pub const MAP_NAME: &str = "Perimeter";
+
 pub const VIEW_DISTANCE: f32 = 15.0;
 
 pub const WORLD_SIZE: f32 = 64.0;
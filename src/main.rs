@@ -27,6 +27,7 @@
     clippy::wildcard_enum_match_arm,
 )]
 
+mod args;
 mod components;
 mod data;
 mod input;
@@ -34,25 +35,42 @@ mod models;
 mod resources;
 mod states;
 mod systems;
+#[cfg(test)]
+mod tests;
 mod utils;
 
+use crate::args::Args;
 use crate::components::Terrain;
 use crate::input::CustomBindingTypes;
+use crate::models::GameType;
+use crate::models::NetConfig;
+use crate::resources::NetRecording;
+use crate::resources::NetResource;
 use crate::resources::State;
+use crate::states::GameState;
 use crate::states::StartupState;
 use crate::systems::net::ConnectionUpdateSystem;
+use crate::systems::net::DiscoverySystem;
 use crate::systems::net::InputSendSystem;
 use crate::systems::net::InterpolationSystem;
 use crate::systems::net::MessageReceiveSystem;
-use crate::systems::net::PositionUpdateSendSystem;
+use crate::systems::net::NetStatsSystem;
+use crate::systems::net::PositionHistorySystem;
 use crate::systems::net::PositionUpdateSystem;
+use crate::systems::net::PredictionSystem;
+use crate::systems::net::RelevanceSystem;
+use crate::systems::net::RosterSystem;
+use crate::systems::net::SnapshotSendSystem;
 use crate::systems::ActorSystem;
 use crate::systems::AiSystem;
 use crate::systems::CameraSystem;
+use crate::systems::ChatSystem;
+use crate::systems::ConsoleSystem;
 use crate::systems::HealthSystem;
 use crate::systems::PhysicsSystem;
 use crate::systems::PlayerSystem;
 use crate::systems::ProjectileSystem;
+use crate::systems::SpectatorSystem;
 use crate::systems::TerrainSystem;
 use crate::systems::UiResizeSystem;
 use crate::systems::WeaponSystem;
@@ -74,36 +92,116 @@ use amethyst::ui::RenderUi;
 use amethyst::ui::UiBundle;
 use amethyst::utils::application_root_dir;
 use amethyst::LoggerConfig;
+use std::net::SocketAddr;
+use std::path::Path;
 
 const FRAME_RATE: u32 = 144;
 
 fn main() -> amethyst::Result<()> {
-    amethyst::start_logger(LoggerConfig::default());
+    let args = Args::parse().map_err(amethyst::Error::from_string)?;
+
+    amethyst::start_logger(LoggerConfig {
+        level_filter: args.log_level,
+        ..LoggerConfig::default()
+    });
 
     let root = application_root_dir()?;
+    let config = args.config_dir.unwrap_or_else(|| root.join("config"));
+
+    let mut net_config = NetConfig::load(config.join("net.ron"))?;
+
+    // Keep bans next to the config rather than wherever the server has been started from
+    if let Some(ban_list) = net_config.ban_list.as_mut() {
+        if ban_list.is_relative() {
+            *ban_list = config.join(ban_list.as_path());
+        }
+    }
+
+    if let Some(conditioner) = args.net_conditioner {
+        net_config.conditioner = Some(conditioner);
+    }
+
+    if let Some(stats_dump) = args.net_stats_dump {
+        net_config.stats_dump = Some(stats_dump);
+    }
+
+    if let Some(player_name) = args.player_name {
+        net_config.player_name = player_name;
+    }
+
+    if args.is_spectator {
+        net_config.spectate = true;
+    }
+
+    if let Some(record) = args.net_record {
+        net_config.record = Some(record);
+    }
+
+    // Dedicated servers may face the internet, so they don't answer discovery unless asked to
+    if args.is_discoverable {
+        net_config.discoverable = true;
+    } else if args.is_headless {
+        net_config.discoverable = false;
+    }
+
+    let replay;
+
+    if let Some(path) = args.replay {
+        let recording = NetRecording::load(&path).map_err(amethyst::Error::from_string)?;
+        let server_address = recording.get_server_address().ok_or_else(|| {
+            amethyst::Error::from_string("The recording has no received messages")
+        })?;
+
+        replay = Some((server_address, recording));
+    } else {
+        replay = None;
+    }
+
+    if let (true, Some(GameType::Server(port))) = (args.is_headless, args.game_type) {
+        return run_headless(&root, net_config, port, args.tick_rate);
+    } else {
+        return run(&root, &config, net_config, args.game_type, replay);
+    }
+}
+
+fn run(
+    root: &Path,
+    config: &Path,
+    net_config: NetConfig,
+    game_type: Option<GameType>,
+    replay: Option<(SocketAddr, NetRecording)>,
+) -> amethyst::Result<()> {
     let game_data = GameDataBuilder::default()
         // Base
         .with_bundle(TransformBundle::new())?
         .with_bundle(
             InputBundle::<CustomBindingTypes>::new()
-                .with_bindings_from_file(root.join("config/input.ron"))?,
+                .with_bindings_from_file(config.join("input.ron"))?,
         )?
         // Game
         .with(HealthSystem.pausable(State::Server), "health", &[])
         .with(InterpolationSystem.pausable(State::Client), "interpolation", &[])
         .with(AiSystem::new().pausable(State::Server), "ai", &[])
-        .with(PlayerSystem.pausable(State::Any), "player", &["input_system"])
+        .with(SpectatorSystem.pausable(State::Client), "spectator", &["input_system"])
+        .with(PlayerSystem.pausable(State::Any), "player", &["input_system", "spectator"])
         .with(ActorSystem.pausable(State::Any), "actor", &["ai", "player", "interpolation"])
         .with(PhysicsSystem::new().pausable(State::Any), "physics", &["actor"])
         .with(InputSendSystem::new().pausable(State::Client), "input_send", &["player", "actor"])
+        .with(PredictionSystem.pausable(State::Client), "prediction", &["physics", "input_send"])
         .with(WeaponSystem::new().pausable(State::Server), "weapon", &["physics"])
-        .with(ProjectileSystem.pausable(State::Any), "projectile", &["physics"])
-        .with(PositionUpdateSendSystem::new().pausable(State::Server), "position_update_send", &["physics"])
+        .with(PositionHistorySystem.pausable(State::Server), "position_history", &["physics"])
+        .with(ProjectileSystem.pausable(State::Any), "projectile", &["physics", "position_history"])
+        .with(RelevanceSystem.pausable(State::Server), "relevance", &["physics"])
+        .with(SnapshotSendSystem::new().pausable(State::Server), "snapshot_send", &["relevance"])
         .with(MessageReceiveSystem.pausable(State::Any), "message_receive", &[])
-        .with(PositionUpdateSystem.pausable(State::Client), "position_update", &["message_receive", "physics"])
-        .with(ConnectionUpdateSystem.pausable(State::Any), "connection_update", &[])
+        .with(PositionUpdateSystem.pausable(State::Client), "position_update", &["message_receive", "prediction"])
+        .with(ConnectionUpdateSystem.pausable(State::Any), "connection_update", &["message_receive", "input_send", "snapshot_send"])
+        .with(RosterSystem::new().pausable(State::Server), "roster", &["connection_update"])
+        .with(NetStatsSystem::new(), "net_stats", &["connection_update"])
+        .with(DiscoverySystem::new(), "discovery", &[])
         .with(CameraSystem.pausable(State::Any), "camera", &[])
         .with(TerrainSystem.pausable(State::Any), "terrain", &[])
+        .with(ChatSystem.pausable(State::Any), "chat", &[])
         // UI
         .with_system_desc(MouseFocusUpdateSystemDesc::default(), "mouse_focus", &[])
         .with_system_desc(CursorHideSystemDesc::default(), "cursor_hide", &["mouse_focus"])
@@ -114,7 +212,7 @@ fn main() -> amethyst::Result<()> {
         .with_bundle(
             RenderingBundle::<DefaultBackend>::new()
                 .with_plugin(RenderToWindow::from_config_path(
-                    root.join("config/display.ron"),
+                    config.join("display.ron"),
                 )?)
                 .with_plugin(RenderFlat2D::default())
                 .with_plugin(RenderUi::default())
@@ -122,10 +220,55 @@ fn main() -> amethyst::Result<()> {
                 .with_plugin(RenderDebugLines::default()),
         )?;
 
-    Application::build(root.join("assets/"), StartupState::new())?
+    Application::build(root.join("assets/"), StartupState::new(game_type, replay))?
+        .with_resource(net_config)
         .with_frame_limit(FrameRateLimitStrategy::Yield, FRAME_RATE)
         .build(game_data)?
         .run();
 
     return Ok(());
 }
+
+/// Runs a dedicated server with simulation systems only, no window and renderer
+fn run_headless(
+    root: &Path,
+    net_config: NetConfig,
+    port: u16,
+    tick_rate: u32,
+) -> amethyst::Result<()> {
+    let game_data = build_headless_game_data()?.with(ConsoleSystem::new(), "console", &[]);
+    let net = NetResource::new_as_server(port, net_config).map_err(amethyst::Error::from_string)?;
+    log::info!("Dedicated server is listening on port {}", port);
+
+    Application::build(
+        root.join("assets/"),
+        GameState::new_headless(GameType::Server(port)),
+    )?
+    .with_resource(net)
+    .with_frame_limit(FrameRateLimitStrategy::Sleep, tick_rate)
+    .build(game_data)?
+    .run();
+
+    return Ok(());
+}
+
+fn build_headless_game_data<'a, 'b>() -> amethyst::Result<GameDataBuilder<'a, 'b>> {
+    return Ok(GameDataBuilder::default()
+        // Base
+        .with_bundle(TransformBundle::new())?
+        // Game
+        .with(HealthSystem.pausable(State::Server), "health", &[])
+        .with(AiSystem::new().pausable(State::Server), "ai", &[])
+        .with(ActorSystem.pausable(State::Server), "actor", &["ai"])
+        .with(PhysicsSystem::new().pausable(State::Server), "physics", &["actor"])
+        .with(WeaponSystem::new().pausable(State::Server), "weapon", &["physics"])
+        .with(PositionHistorySystem.pausable(State::Server), "position_history", &["physics"])
+        .with(ProjectileSystem.pausable(State::Server), "projectile", &["physics", "position_history"])
+        .with(RelevanceSystem.pausable(State::Server), "relevance", &["physics"])
+        .with(SnapshotSendSystem::new().pausable(State::Server), "snapshot_send", &["relevance"])
+        .with(MessageReceiveSystem.pausable(State::Server), "message_receive", &[])
+        .with(ConnectionUpdateSystem.pausable(State::Server), "connection_update", &["message_receive", "snapshot_send"])
+        .with(RosterSystem::new().pausable(State::Server), "roster", &["connection_update"])
+        .with(NetStatsSystem::new(), "net_stats", &["connection_update"])
+        .with(DiscoverySystem::new(), "discovery", &[]));
+}
@@ -0,0 +1,153 @@
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+/// How many zombies a single command may spawn
+const SPAWN_COUNT_MAX: u16 = 100;
+
+/// Commands the server admin types into the console
+#[derive(Debug, PartialEq)]
+pub enum ConsoleCommand {
+    /// Lists players with their ids, addresses and scores
+    Players,
+    /// Disconnects the player with the roster id
+    Kick(u16),
+    /// Disconnects everyone from the address and doesn't let them join again
+    Ban(IpAddr),
+    Say(String),
+    SpawnZombies(u16),
+    /// Starts the game over with the same players
+    Restart,
+    Shutdown,
+}
+
+impl ConsoleCommand {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let mut words = line.split_whitespace();
+        let name = words.next().unwrap_or_default();
+
+        let command = match name {
+            "players" => Self::Players,
+            "kick" => Self::Kick(parse_value("player id", words.next())?),
+            "ban" => Self::Ban(parse_address(words.next())?),
+            "say" => {
+                let text = line.get(name.len()..).unwrap_or_default().trim();
+
+                if text.is_empty() {
+                    return Err("Nothing to say".to_string());
+                } else {
+                    return Ok(Self::Say(text.to_string()));
+                }
+            }
+            "spawn" => {
+                if words.next() != Some("zombie") {
+                    return Err("Only zombies can be spawned".to_string());
+                }
+
+                let count = parse_value("zombie count", words.next())?;
+
+                if count == 0 || count > SPAWN_COUNT_MAX {
+                    return Err(format!(
+                        "Zombie count should be from 1 to {}",
+                        SPAWN_COUNT_MAX
+                    ));
+                }
+
+                Self::SpawnZombies(count)
+            }
+            "restart" => Self::Restart,
+            "shutdown" => Self::Shutdown,
+            "" => {
+                return Err("No command specified".to_string());
+            }
+            _ => {
+                return Err(format!("Unknown command: {}", name));
+            }
+        };
+
+        if let Some(word) = words.next() {
+            return Err(format!("Unexpected argument: {}", word));
+        }
+
+        return Ok(command);
+    }
+}
+
+fn parse_value<T: FromStr>(name: &str, value: Option<&str>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("No {} specified", name))?;
+
+    return value
+        .parse()
+        .map_err(|_| format!("Wrong {}: {}", name, value));
+}
+
+/// Takes either an IP address or a socket address, ignoring the port
+fn parse_address(value: Option<&str>) -> Result<IpAddr, String> {
+    let value = value.ok_or_else(|| "No address specified".to_string())?;
+
+    return value
+        .parse::<IpAddr>()
+        .or_else(|_| value.parse::<SocketAddr>().map(|a| a.ip()))
+        .map_err(|_| format!("Wrong address: {}", value));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Ok(ConsoleCommand::Players),
+            ConsoleCommand::parse(" players")
+        );
+        assert_eq!(Ok(ConsoleCommand::Kick(3)), ConsoleCommand::parse("kick 3"));
+        assert_eq!(
+            Ok(ConsoleCommand::Say("Hello,  everyone".to_string())),
+            ConsoleCommand::parse("say Hello,  everyone "),
+        );
+        assert_eq!(
+            Ok(ConsoleCommand::SpawnZombies(5)),
+            ConsoleCommand::parse("spawn zombie 5"),
+        );
+        assert_eq!(
+            Ok(ConsoleCommand::Restart),
+            ConsoleCommand::parse("restart")
+        );
+        assert_eq!(
+            Ok(ConsoleCommand::Shutdown),
+            ConsoleCommand::parse("shutdown")
+        );
+    }
+
+    #[test]
+    fn test_parse_ban() {
+        let address = IpAddr::from(Ipv4Addr::new(192, 168, 0, 7));
+
+        assert_eq!(
+            Ok(ConsoleCommand::Ban(address)),
+            ConsoleCommand::parse("ban 192.168.0.7"),
+        );
+        assert_eq!(
+            Ok(ConsoleCommand::Ban(address)),
+            ConsoleCommand::parse("ban 192.168.0.7:2003"),
+            "The port is ignored",
+        );
+        assert!(ConsoleCommand::parse("ban 192.168.0").is_err());
+    }
+
+    #[test]
+    fn test_parse_wrong() {
+        assert!(ConsoleCommand::parse("").is_err());
+        assert!(ConsoleCommand::parse("jump").is_err());
+        assert!(ConsoleCommand::parse("kick").is_err(), "No id");
+        assert!(ConsoleCommand::parse("kick Petrukha").is_err());
+        assert!(ConsoleCommand::parse("say  ").is_err());
+        assert!(ConsoleCommand::parse("spawn human 1").is_err());
+        assert!(ConsoleCommand::parse("spawn zombie 0").is_err());
+        assert!(ConsoleCommand::parse("spawn zombie 1000").is_err());
+        assert!(ConsoleCommand::parse("restart now").is_err());
+    }
+}
@@ -1,3 +1,7 @@
+mod console_command;
 mod game_type;
+mod net_config;
 
+pub use self::console_command::*;
 pub use self::game_type::*;
+pub use self::net_config::*;
@@ -0,0 +1,136 @@
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetConfig {
+    /// Told to the server on joining. The server may change it to keep names unique
+    pub player_name: String,
+    /// Join games without an actor just to watch them
+    pub spectate: bool,
+    /// Whether the server lets spectators switch into play
+    pub allow_spectator_play: bool,
+    /// How many players, including spectators and the host, may be in a game at once. No more
+    /// than 16 are let in anyway, so the roster fits a single packet
+    pub players_max: usize,
+    /// How many times a single IP address may try to join within the window. Extra attempts are
+    /// ignored
+    pub join_attempts_max: u32,
+    pub join_attempts_window: Duration,
+    /// How many chat messages a single client may send within the window. Extra ones aren't
+    /// relayed, so a flood can't overload connections of others
+    pub chat_messages_max: u32,
+    pub chat_window: Duration,
+    /// Shown to players looking for servers on the local network
+    pub server_name: String,
+    /// The well-known port which servers answer discovery requests on
+    pub discovery_port: u16,
+    /// Whether a hosted game answers discovery requests. Dedicated servers don't unless asked to
+    pub discoverable: bool,
+    /// A connection is dropped if nothing has been received from it for this long
+    pub timeout: Duration,
+    /// How long the actor of a dropped client is kept for the client to rejoin to it
+    pub session_grace: Duration,
+    /// How long a connection may stay silent before an empty packet is sent to keep it alive
+    pub heartbeat_interval: Duration,
+    /// How many sent messages may wait for acknowledgement per connection
+    pub unacknowledged_messages_max: usize,
+    /// How many early received messages may wait for the missing ones per connection. Messages
+    /// beyond it aren't acknowledged so the peer resends them later
+    pub held_messages_max: usize,
+    pub overflow_policy: NetOverflowPolicy,
+    /// How far back in time hits of projectiles fired by clients may be checked to make up for
+    /// their latency
+    pub rewind_max: Duration,
+    /// Simulates a bad network if set
+    pub conditioner: Option<NetConditionerConfig>,
+    /// How often connection statistics are sampled for the overlay and the dump
+    pub stats_interval: Duration,
+    /// Where to write connection statistics on every sample, if anywhere
+    pub stats_dump: Option<NetStatsDump>,
+    /// Records every sent and received message to the file if set. The file is overwritten on
+    /// every game
+    pub record: Option<PathBuf>,
+    /// Where addresses banned by the server admin are kept, relative to the config directory.
+    /// Bans last until restart if unset
+    pub ban_list: Option<PathBuf>,
+}
+
+/// What to do when a connection has too many unacknowledged messages
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum NetOverflowPolicy {
+    Disconnect,
+    /// Don't send droppable messages until there is room for them. Other messages still lead to
+    /// disconnection
+    DiscardDroppable,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum NetStatsDump {
+    Log,
+    /// Appends rows to the CSV file, writing the header if the file is new
+    Csv(PathBuf),
+}
+
+/// How to spoil packets on their way in and out
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetConditionerConfig {
+    /// Chance of a packet to be lost, from 0 to 1
+    pub loss: f64,
+    /// Delay added to every packet
+    pub latency: Duration,
+    /// Up to this random delay is added on top of the latency
+    pub jitter: Duration,
+    /// Chance of a packet to be delivered twice
+    pub duplication: f64,
+    /// Chance of a packet to fall behind the following ones
+    pub reordering: f64,
+    /// The same seed gives the same packets fate
+    pub seed: u64,
+}
+
+impl Default for NetConfig {
+    fn default() -> Self {
+        return Self {
+            player_name: "Player".to_string(),
+            spectate: false,
+            allow_spectator_play: true,
+            players_max: 16,
+            join_attempts_max: 5,
+            join_attempts_window: Duration::from_secs(10),
+            chat_messages_max: 5,
+            chat_window: Duration::from_secs(5),
+            server_name: "Zombie Shooter".to_string(),
+            discovery_port: 2002,
+            discoverable: true,
+            timeout: Duration::from_secs(10),
+            session_grace: Duration::from_secs(30),
+            heartbeat_interval: Duration::from_secs(1),
+            unacknowledged_messages_max: 256,
+            held_messages_max: 256,
+            overflow_policy: NetOverflowPolicy::DiscardDroppable,
+            rewind_max: Duration::from_millis(300),
+            conditioner: None,
+            stats_interval: Duration::from_secs(1),
+            stats_dump: None,
+            record: None,
+            ban_list: Some(PathBuf::from("bans.txt")),
+        };
+    }
+}
+
+impl Default for NetConditionerConfig {
+    fn default() -> Self {
+        return Self {
+            loss: 0.0,
+            latency: Duration::from_millis(0),
+            jitter: Duration::from_millis(0),
+            duplication: 0.0,
+            reordering: 0.0,
+            seed: 0,
+        };
+    }
+}
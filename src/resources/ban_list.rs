@@ -0,0 +1,100 @@
+use std::collections::HashSet;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::ErrorKind;
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+/// Addresses which aren't allowed to join. Stored one per line in the file if any
+#[derive(Default)]
+pub struct BanList {
+    path: Option<PathBuf>,
+    addresses: HashSet<IpAddr>,
+}
+
+impl BanList {
+    /// A missing file means nobody is banned yet. Malformed lines are skipped
+    pub fn load(path: Option<PathBuf>) -> Result<Self, String> {
+        let mut addresses = HashSet::new();
+
+        if let Some(path) = path.as_ref() {
+            match fs::read_to_string(path) {
+                Ok(content) => {
+                    for line in content.lines().map(str::trim).filter(|l| !l.is_empty()) {
+                        match line.parse() {
+                            Ok(address) => {
+                                addresses.insert(address);
+                            }
+                            Err(_) => {
+                                log::warn!("Skipped a wrong address in {:?}: {}", path, line);
+                            }
+                        }
+                    }
+                }
+                Err(error) => {
+                    if error.kind() != ErrorKind::NotFound {
+                        return Err(format!("Failed to load bans from {:?}: {}", path, error));
+                    }
+                }
+            }
+        }
+
+        return Ok(Self { path, addresses });
+    }
+
+    /// Bans the address and appends it to the file. The address stays banned even if the file
+    /// can't be written
+    pub fn add(&mut self, address: IpAddr) -> Result<(), String> {
+        if !self.addresses.insert(address) {
+            return Ok(());
+        }
+
+        if let Some(path) = self.path.as_ref() {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+
+            writeln!(file, "{}", address)
+                .map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+        }
+
+        return Ok(());
+    }
+
+    pub fn contains(&self, address: IpAddr) -> bool {
+        return self.addresses.contains(&address);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_add_load() -> Result<(), String> {
+        let path = std::env::temp_dir().join(format!("shooter_bans_{}.txt", std::process::id()));
+        let banned = IpAddr::from(Ipv4Addr::new(192, 168, 0, 7));
+        let other = IpAddr::from(Ipv4Addr::new(192, 168, 0, 8));
+
+        let mut bans = BanList::load(Some(path.clone()))?;
+        assert!(!bans.contains(banned), "No file yet");
+
+        bans.add(banned)?;
+        bans.add(banned)?;
+        assert!(bans.contains(banned));
+
+        let loaded = BanList::load(Some(path.clone()));
+        let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        fs::remove_file(&path).map_err(|e| e.to_string())?;
+
+        assert_eq!("192.168.0.7\n", content, "Written once");
+        assert!(loaded.as_ref().map_or(false, |b| b.contains(banned)));
+        assert!(loaded.map_or(false, |b| !b.contains(other)));
+
+        return Ok(());
+    }
+}
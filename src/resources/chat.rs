@@ -0,0 +1,125 @@
+use crate::utils;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+pub const CHAT_TEXT_LENGTH_MAX: usize = 100;
+
+/// How many recent lines are kept on screen
+pub const CHAT_LINES_MAX: usize = 5;
+
+pub struct ChatLine {
+    pub text: String,
+    /// When the line has been received, in real time
+    pub time: Duration,
+}
+
+/// Recent chat lines and the text being typed if the chat input is open
+#[derive(Default)]
+pub struct ChatResource {
+    lines: VecDeque<ChatLine>,
+    input: Option<String>,
+}
+
+impl ChatResource {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    pub fn add_line(&mut self, sender: &str, text: &str, time: Duration) {
+        if self.lines.len() >= CHAT_LINES_MAX {
+            self.lines.pop_front();
+        }
+
+        self.lines.push_back(ChatLine {
+            text: format!("{}: {}", sender, text),
+            time,
+        });
+    }
+
+    pub fn open_input(&mut self) {
+        self.input = Some(String::new());
+    }
+
+    /// Closes the input and returns the typed text unless it's blank
+    pub fn close_input(&mut self) -> Option<String> {
+        return self
+            .input
+            .take()
+            .map(|t| sanitize_chat_text(&t))
+            .filter(|t| !t.is_empty());
+    }
+
+    /// Types the character if the input is open and has room for it
+    pub fn type_char(&mut self, c: char) {
+        if let Some(input) = self.input.as_mut() {
+            if !c.is_control() && input.chars().count() < CHAT_TEXT_LENGTH_MAX {
+                input.push(c);
+            }
+        }
+    }
+
+    pub fn erase_char(&mut self) {
+        if let Some(input) = self.input.as_mut() {
+            input.pop();
+        }
+    }
+
+    pub fn get_lines(&self) -> &VecDeque<ChatLine> {
+        return &self.lines;
+    }
+
+    pub fn get_input(&self) -> Option<&str> {
+        return self.input.as_deref();
+    }
+
+    pub const fn is_typing(&self) -> bool {
+        return self.input.is_some();
+    }
+}
+
+pub fn sanitize_chat_text(text: &str) -> String {
+    return utils::sanitize_text(text, CHAT_TEXT_LENGTH_MAX);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input() {
+        let mut chat = ChatResource::new();
+
+        chat.type_char('x');
+        assert_eq!(None, chat.get_input(), "Not typing yet");
+
+        chat.open_input();
+        "  hi!\r".chars().for_each(|c| chat.type_char(c));
+        chat.erase_char();
+        chat.type_char('?');
+
+        assert!(chat.is_typing());
+        assert_eq!(Some("  hi?"), chat.get_input());
+        assert_eq!(Some("hi?".to_string()), chat.close_input());
+        assert!(!chat.is_typing());
+
+        chat.open_input();
+        chat.type_char(' ');
+        assert_eq!(None, chat.close_input(), "Blank");
+    }
+
+    #[test]
+    fn test_add_line() {
+        let mut chat = ChatResource::new();
+
+        for i in 0..=CHAT_LINES_MAX {
+            chat.add_line("Petrukha", &i.to_string(), Duration::from_secs(i as u64));
+        }
+
+        assert_eq!(CHAT_LINES_MAX, chat.get_lines().len());
+        assert_eq!(
+            Some("Petrukha: 1"),
+            chat.get_lines().front().map(|l| l.text.as_str()),
+            "The oldest line is gone",
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use bincode::Options;
+use serde::Deserialize;
+use serde::Serialize;
+use std::io::ErrorKind;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use std::net::UdpSocket;
+use std::time::Duration;
+use std::time::Instant;
+
+const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A server is forgotten if it hasn't answered for this long
+const SERVER_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Discovery datagrams are tiny, so anything bigger is garbage
+const DATAGRAM_SIZE_MAX: usize = 512;
+
+/// Discovery goes through its own socket, so it doesn't depend on the game protocol version
+#[derive(Serialize, Deserialize, Debug)]
+pub enum DiscoveryMessage {
+    Request,
+    Response(ServerInfo),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ServerInfo {
+    /// Random for every server, so the one answering from several addresses is listed once
+    pub id: u64,
+    pub name: String,
+    pub players: u16,
+    pub map: String,
+    pub protocol_version: u16,
+    pub build: String,
+    /// The game port, which differs from the discovery one
+    pub port: u16,
+}
+
+/// Answers discovery requests on the well-known port. Only one server per machine can listen to it
+pub struct DiscoveryResponder {
+    socket: UdpSocket,
+}
+
+/// Looks for servers on loopback and local subnets
+pub struct LanDiscovery {
+    socket: UdpSocket,
+    port: u16,
+    servers: Vec<DiscoveredServer>,
+    last_request: Option<Instant>,
+}
+
+pub struct DiscoveredServer {
+    /// The address to join
+    pub address: SocketAddr,
+    pub info: ServerInfo,
+    last_seen: Instant,
+}
+
+impl DiscoveryMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        // I use unwrap here since I suppose there's nothing to worry about
+        #[allow(clippy::unwrap_used)]
+        return options().serialize(self).unwrap();
+    }
+
+    pub fn decode(encoded: &[u8]) -> Result<Self, bincode::Error> {
+        return options().deserialize(encoded);
+    }
+}
+
+impl DiscoveryResponder {
+    pub fn new(port: u16) -> Result<Self, String> {
+        let socket =
+            UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port)).map_err(|e| format!("{}", e))?;
+        socket.set_nonblocking(true).map_err(|e| format!("{}", e))?;
+        return Ok(Self { socket });
+    }
+
+    /// Returns addresses which have asked for the server info. Requests from outside the local
+    /// network are ignored, so the server can't be used to flood a spoofed address with responses
+    pub fn take_requests(&self) -> Vec<SocketAddr> {
+        let mut requesters = Vec::new();
+
+        for (address, datagram) in receive(&self.socket) {
+            if !is_local(address.ip()) {
+                log::debug!("Ignored a discovery request from {}", address);
+                continue;
+            }
+
+            if let Ok(DiscoveryMessage::Request) = DiscoveryMessage::decode(&datagram) {
+                requesters.push(address);
+            }
+        }
+
+        return requesters;
+    }
+
+    pub fn respond(&self, address: SocketAddr, info: ServerInfo) {
+        let response = DiscoveryMessage::Response(info).encode();
+
+        if let Err(error) = self.socket.send_to(&response, address) {
+            log::warn!(
+                "Failed to answer a discovery request of {}: {}",
+                address,
+                error
+            );
+        }
+    }
+}
+
+impl LanDiscovery {
+    pub fn new(port: u16) -> Result<Self, String> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).map_err(|e| format!("{}", e))?;
+        socket.set_nonblocking(true).map_err(|e| format!("{}", e))?;
+        socket.set_broadcast(true).map_err(|e| format!("{}", e))?;
+
+        return Ok(Self {
+            socket,
+            port,
+            servers: Vec::new(),
+            last_request: None,
+        });
+    }
+
+    /// Asks for servers now and then, collects their answers and forgets the silent ones. Returns
+    /// `true` if the list of servers has changed
+    pub fn update(&mut self) -> bool {
+        let now = Instant::now();
+
+        if self
+            .last_request
+            .map_or(true, |t| now.duration_since(t) > REQUEST_INTERVAL)
+        {
+            self.request();
+            self.last_request = Some(now);
+        }
+
+        let mut is_changed = false;
+
+        for (address, datagram) in receive(&self.socket) {
+            if let Ok(DiscoveryMessage::Response(info)) = DiscoveryMessage::decode(&datagram) {
+                let address = SocketAddr::new(address.ip(), info.port);
+
+                if let Some(server) = self.servers.iter_mut().find(|s| s.info.id == info.id) {
+                    is_changed |= server.info != info;
+                    server.info = info;
+                    server.last_seen = now;
+                } else {
+                    self.servers.push(DiscoveredServer {
+                        address,
+                        info,
+                        last_seen: now,
+                    });
+
+                    is_changed = true;
+                }
+            }
+        }
+
+        let count = self.servers.len();
+
+        self.servers
+            .retain(|s| now.duration_since(s.last_seen) < SERVER_TIMEOUT);
+
+        if is_changed || self.servers.len() != count {
+            self.servers.sort_by_key(|s| s.address);
+            return true;
+        } else {
+            return false;
+        }
+    }
+
+    fn request(&self) {
+        let request = DiscoveryMessage::Request.encode();
+
+        // A broadcast may not reach servers on this machine, so ask them directly
+        for ip in &[Ipv4Addr::BROADCAST, Ipv4Addr::LOCALHOST] {
+            if let Err(error) = self.socket.send_to(&request, (*ip, self.port)) {
+                log::debug!("Failed to send a discovery request to {}: {}", ip, error);
+            }
+        }
+    }
+
+    pub fn get_servers(&self) -> &[DiscoveredServer] {
+        return &self.servers;
+    }
+}
+
+fn receive(socket: &UdpSocket) -> Vec<(SocketAddr, Vec<u8>)> {
+    let mut datagrams = Vec::new();
+    let mut buffer = [0; DATAGRAM_SIZE_MAX];
+
+    loop {
+        match socket.recv_from(&mut buffer) {
+            Ok((length, address)) => {
+                datagrams.push((address, buffer.get(..length).unwrap_or_default().to_vec()));
+            }
+            Err(error) => {
+                if error.kind() != ErrorKind::WouldBlock {
+                    log::debug!("Failed to receive a discovery datagram: {}", error);
+                }
+
+                break;
+            }
+        }
+    }
+
+    return datagrams;
+}
+
+fn is_local(ip: IpAddr) -> bool {
+    return match ip {
+        IpAddr::V4(ip) => ip.is_private() || ip.is_loopback() || ip.is_link_local(),
+        IpAddr::V6(ip) => ip.is_loopback(),
+    };
+}
+
+fn options() -> impl Options {
+    return bincode::DefaultOptions::new().with_varint_encoding();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_local() {
+        assert!(is_local(IpAddr::from(Ipv4Addr::LOCALHOST)));
+        assert!(is_local(IpAddr::from(Ipv4Addr::new(192, 168, 0, 7))));
+        assert!(is_local(IpAddr::from(Ipv4Addr::new(10, 0, 0, 7))));
+        assert!(!is_local(IpAddr::from(Ipv4Addr::new(8, 8, 8, 8))));
+    }
+}
@@ -42,4 +42,9 @@ impl EntityConverter {
             }
         }
     }
+
+    /// Forgets every entity and returns them, so they can be deleted
+    pub fn take_entities(&mut self) -> Vec<Entity> {
+        return self.data.drain(..).map(|r| r.entity).collect();
+    }
 }
@@ -1,5 +1,7 @@
 use crate::components::ActorActions;
 use crate::components::ActorType;
+use crate::models::ConsoleCommand;
+use crate::resources::RosterPlayer;
 use crate::utils::Position;
 use amethyst::ecs::Entity;
 use std::net::SocketAddr;
@@ -8,23 +10,44 @@ pub type GameTaskResource = Vec<GameTask>;
 
 pub enum GameTask {
     Start,
-    ClientJoin(SocketAddr),
+    ClientJoin {
+        address: SocketAddr,
+        protocol_version: u16,
+        build: String,
+    },
+    /// A client of the same version has told about itself
+    ClientJoinDetails {
+        address: SocketAddr,
+        name: String,
+        is_spectator: bool,
+        session_token: Option<u64>,
+    },
+    /// A spectating client wants to play
+    PlayRequest(SocketAddr),
     ActorSpawn {
         entity: Entity,
         actor_type: &'static ActorType,
         position: Position,
+        health: f32,
     },
     ActorGrant {
         entity: Entity,
     },
+    /// The server has told the actor's health
+    ActorHealth {
+        entity: Entity,
+        health: f32,
+    },
     ActorAction {
         entity: Entity,
         actions: ActorActions,
         direction: f32,
+        input_sequence: u16,
     },
     ActorTurn {
         entity: Entity,
         direction: f32,
+        input_sequence: u16,
     },
     ProjectileSpawn {
         position: Position,
@@ -36,6 +59,23 @@ pub enum GameTask {
         entity: Entity,
         force_x: f32,
         force_y: f32,
+        shooter: Option<Entity>,
     },
     EntityDelete(Entity),
+    /// The server has told the players of the game
+    Roster(Vec<RosterPlayer>),
+    /// A player wants to say something to everyone
+    ChatSay {
+        sender: Entity,
+        text: String,
+    },
+    /// The server has relayed what a player has said
+    Chat {
+        sender: String,
+        text: String,
+    },
+    /// The server admin has typed the command
+    ConsoleCommand(ConsoleCommand),
+    /// The client has lost connection to the server for the specified reason
+    Disconnected(String),
 }
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Limits how often an IP address may try to join, so it can't flood the server with connections
+pub struct JoinLimiter {
+    attempts_max: u32,
+    window: Duration,
+    /// When the address has started the current window and how many times it has tried since
+    attempts: HashMap<IpAddr, (Instant, u32)>,
+}
+
+impl JoinLimiter {
+    pub fn new(attempts_max: u32, window: Duration) -> Self {
+        return Self {
+            attempts_max,
+            window,
+            attempts: HashMap::new(),
+        };
+    }
+
+    /// Counts the attempt and returns whether it's allowed
+    pub fn try_join(&mut self, address: IpAddr, now: Instant) -> bool {
+        let window = self.window;
+
+        self.attempts
+            .retain(|_, (started, _)| now.duration_since(*started) < window);
+
+        let (_, count) = self.attempts.entry(address).or_insert((now, 0));
+        *count = count.saturating_add(1);
+
+        return *count <= self.attempts_max;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_try_join() {
+        let mut limiter = JoinLimiter::new(2, Duration::from_secs(10));
+        let address = IpAddr::from(Ipv4Addr::new(192, 168, 0, 7));
+        let other = IpAddr::from(Ipv4Addr::new(192, 168, 0, 8));
+        let now = Instant::now();
+
+        assert!(limiter.try_join(address, now));
+        assert!(limiter.try_join(address, now + Duration::from_secs(1)));
+        assert!(!limiter.try_join(address, now + Duration::from_secs(2)));
+        assert!(limiter.try_join(other, now + Duration::from_secs(2)));
+        assert!(
+            limiter.try_join(address, now + Duration::from_secs(10)),
+            "The window is over",
+        );
+    }
+}
@@ -1,29 +1,54 @@
 use crate::components::ActorTypeSerialized;
+use crate::resources::QuantizedPosition;
+use crate::resources::RosterPlayer;
 use crate::utils::Position;
 use bincode::Options;
 use serde::Deserialize;
 use serde::Serialize;
 
-pub const MESSAGE_SIZE_MAX: usize = std::mem::size_of::<Message>();
+/// Increase on every change of messages or packets format
+pub const PROTOCOL_VERSION: u16 = 12;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Message {
-    Response {
-        message_id: u16,
-    },
+    /// Keep its layout and place stable across protocol versions, as well as of `JoinReject`, so
+    /// a server can tell a client of another version about the mismatch. Anything else goes to
+    /// `JoinDetails`
     Join {
         id: u16,
+        protocol_version: u16,
+        build: String,
     },
     JoinAccept {
         id: u16,
+        /// Lets the client rejoin to the same actor after losing connection
+        session_token: u64,
+    },
+    JoinReject {
+        id: u16,
+        reason: String,
+    },
+    /// The server has the same version as the client, so the client may tell about itself
+    JoinProceed {
+        id: u16,
+    },
+    JoinDetails {
+        id: u16,
+        name: String,
+        /// Join without an actor just to watch the game
+        is_spectator: bool,
+        /// Given by the server on the previous join to get the same actor back
+        session_token: Option<u64>,
     },
     ClientInput {
         id: u16,
+        input_sequence: u16,
         actions: u8,
         direction: f32,
     },
     ClientInputDirection {
         id: u16,
+        input_sequence: u16,
         direction: f32,
     },
     ActorSpawn {
@@ -31,14 +56,36 @@ pub enum Message {
         entity_id: u32,
         actor_type: ActorTypeSerialized,
         position: Position,
+        health: f32,
     },
     ActorGrant {
         id: u16,
         entity_id: u32,
     },
-    PositionUpdate {
+    /// The actor has been hit and has the health left
+    ActorDamage {
+        id: u16,
         entity_id: u32,
-        position: Position,
+        health: f32,
+    },
+    ActorDeath {
+        id: u16,
+        entity_id: u32,
+    },
+    /// Entity positions at the tick, delta encoded against the base snapshot if any. Split into
+    /// several parts of the same tick if it doesn't fit a packet
+    Snapshot {
+        tick: u16,
+        base_tick: Option<u16>,
+        part: u8,
+        parts: u8,
+        entities: Vec<(u32, QuantizedPosition)>,
+        removed: Vec<u32>,
+        /// The latest input of the receiving client applied by the server
+        input: Option<InputEcho>,
+    },
+    SnapshotAck {
+        tick: u16,
     },
     ProjectileSpawn {
         id: u16,
@@ -51,6 +98,41 @@ pub enum Message {
         id: u16,
         entity_id: u32,
     },
+    /// A client is leaving the game
+    Leave {
+        id: u16,
+        reason: String,
+    },
+    /// Every player of the game with their scores
+    Roster {
+        id: u16,
+        players: Vec<RosterPlayer>,
+    },
+    /// A spectating client wants to get an actor and play
+    PlayRequest {
+        id: u16,
+    },
+    /// A client says something to everyone
+    ChatSay {
+        id: u16,
+        text: String,
+    },
+    /// The server relays what a player has said
+    Chat {
+        id: u16,
+        sender: String,
+        text: String,
+    },
+    /// The server is going down, so clients should leave the game
+    ServerShutdown {
+        id: u16,
+        reason: String,
+    },
+    /// The server admin has disconnected the client
+    Kick {
+        id: u16,
+        reason: String,
+    },
 }
 
 impl Message {
@@ -63,20 +145,30 @@ impl Message {
             .unwrap();
     }
 
-    pub fn decode(encoded: &[u8]) -> Result<Self, bincode::Error> {
+    /// Decodes a message from the beginning of the data and moves the data past it
+    pub fn decode_from(encoded: &mut &[u8]) -> Result<Self, bincode::Error> {
         return bincode::DefaultOptions::new()
             .with_varint_encoding()
-            .deserialize(encoded);
+            .allow_trailing_bytes()
+            .deserialize_from(encoded);
     }
 
     pub fn set_id(&mut self, id_new: u16) {
         #[allow(clippy::match_same_arms)]
         match *self {
-            Self::Response { .. } => {}
-            Self::Join { ref mut id } => {
+            Self::Join { ref mut id, .. } => {
+                *id = id_new;
+            }
+            Self::JoinAccept { ref mut id, .. } => {
+                *id = id_new;
+            }
+            Self::JoinReject { ref mut id, .. } => {
+                *id = id_new;
+            }
+            Self::JoinProceed { ref mut id } => {
                 *id = id_new;
             }
-            Self::JoinAccept { ref mut id } => {
+            Self::JoinDetails { ref mut id, .. } => {
                 *id = id_new;
             }
             Self::ClientInput { ref mut id, .. } => {
@@ -91,37 +183,123 @@ impl Message {
             Self::ActorGrant { ref mut id, .. } => {
                 *id = id_new;
             }
-            Self::PositionUpdate { .. } => {}
+            Self::ActorDamage { ref mut id, .. } => {
+                *id = id_new;
+            }
+            Self::ActorDeath { ref mut id, .. } => {
+                *id = id_new;
+            }
+            Self::Snapshot { .. } => {}
+            Self::SnapshotAck { .. } => {}
             Self::ProjectileSpawn { ref mut id, .. } => {
                 *id = id_new;
             }
             Self::EntityDelete { ref mut id, .. } => {
                 *id = id_new;
             }
+            Self::Leave { ref mut id, .. } => {
+                *id = id_new;
+            }
+            Self::Roster { ref mut id, .. } => {
+                *id = id_new;
+            }
+            Self::PlayRequest { ref mut id } => {
+                *id = id_new;
+            }
+            Self::ChatSay { ref mut id, .. } => {
+                *id = id_new;
+            }
+            Self::Chat { ref mut id, .. } => {
+                *id = id_new;
+            }
+            Self::ServerShutdown { ref mut id, .. } => {
+                *id = id_new;
+            }
+            Self::Kick { ref mut id, .. } => {
+                *id = id_new;
+            }
         }
     }
 
     pub const fn get_id(&self) -> Option<u16> {
         #[allow(clippy::match_same_arms)]
         return match *self {
-            Self::Response { .. } => None,
-            Self::Join { id } => Some(id),
-            Self::JoinAccept { id } => Some(id),
+            Self::Join { id, .. } => Some(id),
+            Self::JoinAccept { id, .. } => Some(id),
+            Self::JoinReject { id, .. } => Some(id),
+            Self::JoinProceed { id } => Some(id),
+            Self::JoinDetails { id, .. } => Some(id),
             Self::ClientInput { id, .. } => Some(id),
             Self::ClientInputDirection { id, .. } => Some(id),
             Self::ActorSpawn { id, .. } => Some(id),
             Self::ActorGrant { id, .. } => Some(id),
-            Self::PositionUpdate { .. } => None,
+            Self::ActorDamage { id, .. } => Some(id),
+            Self::ActorDeath { id, .. } => Some(id),
+            Self::Snapshot { .. } => None,
+            Self::SnapshotAck { .. } => None,
             Self::ProjectileSpawn { id, .. } => Some(id),
             Self::EntityDelete { id, .. } => Some(id),
+            Self::Leave { id, .. } => Some(id),
+            Self::Roster { id, .. } => Some(id),
+            Self::PlayRequest { id } => Some(id),
+            Self::ChatSay { id, .. } => Some(id),
+            Self::Chat { id, .. } => Some(id),
+            Self::ServerShutdown { id, .. } => Some(id),
+            Self::Kick { id, .. } => Some(id),
         };
     }
 
     pub const fn has_id(&self) -> bool {
-        if let Self::Response { .. } = *self {
-            return false;
-        } else {
-            return true;
-        }
+        return !matches!(*self, Self::Snapshot { .. } | Self::SnapshotAck { .. });
+    }
+
+    /// Whether the message may be discarded instead of sent when the connection is overloaded
+    pub const fn is_droppable(&self) -> bool {
+        return matches!(
+            *self,
+            Self::ClientInputDirection { .. } | Self::ProjectileSpawn { .. }
+        );
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct InputEcho {
+    pub sequence: u16,
+    /// How long the input has been applied for, in milliseconds
+    pub age: u16,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes the fields as if they were of a message
+    fn encode_fields<T: Serialize>(fields: &T) -> bincode::Result<Vec<u8>> {
+        return bincode::DefaultOptions::new()
+            .with_varint_encoding()
+            .serialize(fields);
+    }
+
+    #[test]
+    fn test_join_layout() -> bincode::Result<()> {
+        let join = Message::Join {
+            id: 1,
+            protocol_version: 5,
+            build: "build".to_string(),
+        };
+
+        let reject = Message::JoinReject {
+            id: 1,
+            reason: "reason".to_string(),
+        };
+
+        // The variant index goes first
+        assert_eq!(
+            encode_fields(&(0_u32, 1_u16, 5_u16, "build"))?,
+            join.encode()
+        );
+        assert_eq!(encode_fields(&(2_u32, 1_u16, "reason"))?, reject.encode());
+
+        return Ok(());
     }
 }
@@ -1,21 +1,43 @@
+mod ban_list;
+mod chat;
+mod discovery;
 mod entity_converter;
 mod game_status;
 mod game_task;
 mod input;
+mod join_limiter;
 mod message;
 mod net;
+mod net_conditioner;
+mod net_recording;
+mod packet;
+mod position_history;
 mod position_update;
+mod prediction;
+mod roster;
+mod snapshot;
 mod sprite;
 mod state;
 mod wallpaper;
 
+pub use self::ban_list::*;
+pub use self::chat::*;
+pub use self::discovery::*;
 pub use self::entity_converter::*;
 pub use self::game_status::*;
 pub use self::game_task::*;
 pub use self::input::*;
+pub use self::join_limiter::*;
 pub use self::message::*;
 pub use self::net::*;
+pub use self::net_conditioner::*;
+pub use self::net_recording::*;
+pub use self::packet::*;
+pub use self::position_history::*;
 pub use self::position_update::*;
+pub use self::prediction::*;
+pub use self::roster::*;
+pub use self::snapshot::*;
 pub use self::sprite::*;
 pub use self::state::*;
 pub use self::wallpaper::*;
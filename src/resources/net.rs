@@ -1,30 +1,91 @@
+use crate::data::BUILD_HASH;
+use crate::data::POSITION_UPDATE_INTERVAL;
+use crate::models::NetConfig;
+use crate::models::NetOverflowPolicy;
+use crate::resources::BanList;
+use crate::resources::InputEcho;
+use crate::resources::JoinLimiter;
 use crate::resources::Message;
+use crate::resources::NetConditioner;
+use crate::resources::NetDirection;
+use crate::resources::NetRecorder;
+use crate::resources::NetRecording;
+use crate::resources::Packet;
+use crate::resources::PacketHeader;
+use crate::resources::MESSAGE_SIZE_MAX;
+use crate::resources::PACKET_SIZE_MAX;
+use crate::resources::PROTOCOL_VERSION;
+use crate::utils::RttEstimator;
+use crate::utils::SequenceExt;
+use crate::utils::TakeContent;
 use amethyst::ecs::Entity;
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::io::ErrorKind;
+use std::net::IpAddr;
 use std::net::SocketAddr;
 use std::net::UdpSocket;
 use std::time::Duration;
 use std::time::Instant;
 
-const MESSAGE_RESEND_INTERVAL: Duration = Duration::from_millis(400); // TODO: Tweak
+/// How many messages after the last acknowledged one can be acknowledged selectively
+const ACK_BITS_COUNT: u16 = 32;
 
 pub struct NetResource {
     is_server: bool,
+    config: NetConfig,
     pub socket: UdpSocket,
     pub connections: HashMap<SocketAddr, NetConnection>,
+    incoming_conditioner: Option<NetConditioner>,
+    outgoing_conditioner: Option<NetConditioner>,
+    recorder: Option<NetRecorder>,
+    /// Incoming messages are taken from the recording instead of the socket if set
+    replay: Option<(NetRecording, Instant)>,
+    /// Dropped sessions waiting for their clients to rejoin, by token
+    sessions: HashMap<u64, NetSession>,
+    bans: BanList,
+    join_limiter: JoinLimiter,
 }
 
 pub struct NetConnection {
     status: NetConnectionStatus,
-    // TODO: Maybe don't allow grow to large
     unacknowledged_messages: HashMap<u16, UnacknowledgedMessage>,
-    // TODO: Maybe don't allow grow to large
+    unacknowledged_messages_max: usize,
+    unreliable_messages: Vec<Vec<u8>>,
     held_messages: HashMap<u16, Message>,
-    // TODO: Handle ID restart
+    held_messages_max: usize,
+    overflow_policy: NetOverflowPolicy,
+    discarded_messages: u64,
+    deferred_messages: u64,
+    resent_messages: u64,
+    packets_sent: u64,
+    packets_received: u64,
+    bytes_sent: u64,
+    bytes_received: u64,
     next_incoming_message_id: u16,
     next_outgoing_message_id: u16,
+    is_ack_pending: bool,
+    last_received: Instant,
+    last_sent: Instant,
+    rtt: RttEstimator,
+    acknowledged_snapshot: Option<u16>,
+    /// The latest applied input sequence and when it was applied
+    applied_input: Option<(u16, Instant)>,
+    chat_messages_max: u32,
+    chat_window: Duration,
+    /// When the current chat window has started and how many messages the client has sent since
+    chat_messages: (Instant, u32),
+    /// Entities spawned on the client
+    relevant_entities: HashSet<Entity>,
     pub attached_entity: Option<Entity>,
+    /// Spectators have a bare entity attached to identify them instead of an actor
+    pub is_spectator: bool,
+    /// Lets the client rejoin to the attached entity after losing connection. Cleared on leaving
+    pub session_token: Option<u64>,
+    /// Told to the server once it lets the client proceed joining
+    join_details: Option<Message>,
 }
 
 pub enum NetConnectionStatus {
@@ -32,76 +93,481 @@ pub enum NetConnectionStatus {
     Disconnected(String),
 }
 
+/// A snapshot of connection statistics. Counters are totals since the connection has been created
+#[derive(Clone, Copy, Default)]
+pub struct NetMetrics {
+    pub rtt: Option<Duration>,
+    pub unacknowledged: usize,
+    pub held: usize,
+    /// Total count of droppable messages which haven't been sent due to overflow
+    pub discarded: u64,
+    /// Total count of received messages which haven't been taken due to overflow
+    pub deferred: u64,
+    /// Total count of reliable messages sent again after a timeout
+    pub resent: u64,
+    pub packets_sent: u64,
+    pub packets_received: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+/// An entity kept after its connection has been dropped
+struct NetSession {
+    entity: Entity,
+    is_spectator: bool,
+    dropped: Instant,
+}
+
 struct UnacknowledgedMessage {
     data: Vec<u8>,
-    last_sent: Instant,
+    /// Empty until the message is flushed for the first time
+    last_sent: Option<Instant>,
+    resends: u32,
 }
 
 impl NetResource {
-    pub fn new_as_server(port: u16) -> Result<Self, String> {
-        return Self::new(&format!("0.0.0.0:{}", port), true);
+    pub fn new_as_server(port: u16, config: NetConfig) -> Result<Self, String> {
+        let bans = BanList::load(config.ban_list.clone())?;
+        let mut network = Self::new(&format!("0.0.0.0:{}", port), true, config)?;
+        network.bans = bans;
+        return Ok(network);
     }
 
-    pub fn new_as_client(server_address: SocketAddr) -> Result<Self, String> {
-        let mut network = Self::new("0.0.0.0:0", false)?;
-
-        network
-            .connections
-            .insert(server_address, NetConnection::new());
+    pub fn new_as_client(server_address: SocketAddr, config: NetConfig) -> Result<Self, String> {
+        let mut network = Self::new("0.0.0.0:0", false, config)?;
+        network.get_or_create_connection(server_address);
+        network.send_join(None);
+        return Ok(network);
+    }
 
-        network.send_to_all(Message::Join { id: 0 });
+    /// Plays the recorded session back as if it came from the server. Nothing is sent
+    pub fn new_as_replay(
+        server_address: SocketAddr,
+        recording: NetRecording,
+        config: NetConfig,
+    ) -> Result<Self, String> {
+        let mut network = Self::new("127.0.0.1:0", false, config)?;
+        network.get_or_create_connection(server_address);
+        network.replay = Some((recording, Instant::now()));
 
         return Ok(network);
     }
 
-    fn new(address: &str, is_server: bool) -> Result<Self, String> {
-        let socket = UdpSocket::bind(address).map_err(|e| format!("{}", e))?;
-        socket.set_nonblocking(true).map_err(|e| format!("{}", e))?;
+    fn new(address: &str, is_server: bool, config: NetConfig) -> Result<Self, String> {
+        let socket = bind_socket(address)?;
+
+        let join_limiter = JoinLimiter::new(config.join_attempts_max, config.join_attempts_window);
+        let incoming_conditioner = config.conditioner.map(|c| NetConditioner::new(c, c.seed));
+        let outgoing_conditioner = config
+            .conditioner
+            .map(|c| NetConditioner::new(c, c.seed.wrapping_add(1)));
+
+        let recorder;
+
+        if let Some(path) = config.record.as_ref() {
+            // The game is still playable without recording
+            match NetRecorder::create(path) {
+                Ok(created) => {
+                    recorder = Some(created);
+                    log::info!("Recording network messages to {:?}", path);
+                }
+                Err(error) => {
+                    recorder = None;
+                    log::warn!("Not recording to {:?}: {}", path, error);
+                }
+            }
+        } else {
+            recorder = None;
+        }
 
         return Ok(Self {
             is_server,
+            config,
             socket,
             connections: HashMap::new(),
+            incoming_conditioner,
+            outgoing_conditioner,
+            recorder,
+            replay: None,
+            sessions: HashMap::new(),
+            bans: BanList::default(),
+            join_limiter,
         });
     }
 
-    pub fn update_connections(&mut self) {
-        let mut disconnected = Vec::new();
+    fn send_join(&mut self, session_token: Option<u64>) {
+        let name = self.config.player_name.clone();
+        let is_spectator = self.config.spectate;
 
-        for (address, connection) in &mut self.connections {
-            connection.resend_unacknowledged_messages(&self.socket, address);
+        for connection in self.connections.values_mut() {
+            connection.join_details = Some(Message::JoinDetails {
+                id: 0,
+                name: name.clone(),
+                is_spectator,
+                session_token,
+            });
+        }
+
+        self.send_to_all(Message::Join {
+            id: 0,
+            protocol_version: PROTOCOL_VERSION,
+            build: BUILD_HASH.to_string(),
+        });
+    }
+
+    /// Replaces the lost connection to the server with a new one and asks to get the same
+    /// session back. A new socket is bound so the server doesn't take the new connection for the
+    /// old one if it hasn't dropped it yet
+    pub fn rejoin(&mut self, server_address: SocketAddr, session_token: u64) {
+        match bind_socket("0.0.0.0:0") {
+            Ok(socket) => {
+                self.socket = socket;
+            }
+            Err(error) => {
+                log::warn!("Failed to bind a new socket to rejoin: {}", error);
+            }
+        }
+
+        self.connections.remove(&server_address);
+        self.get_or_create_connection(server_address);
+        self.send_join(Some(session_token));
+    }
+
+    pub fn get_or_create_connection(&mut self, address: SocketAddr) -> &mut NetConnection {
+        let config = &self.config;
+
+        return self
+            .connections
+            .entry(address)
+            .or_insert_with(|| NetConnection::new(config));
+    }
+
+    /// Takes received packets, passing them through the conditioner if any, and records them
+    pub fn receive(&mut self) -> Vec<(SocketAddr, Vec<u8>)> {
+        let packets = self.receive_unrecorded();
+
+        if let Some(recorder) = self.recorder.as_mut() {
+            for (address, packet) in &packets {
+                recorder.record_packet(NetDirection::Incoming, *address, packet);
+            }
+        }
+
+        return packets;
+    }
+
+    fn receive_unrecorded(&mut self) -> Vec<(SocketAddr, Vec<u8>)> {
+        if let Some((recording, started)) = self.replay.as_mut() {
+            return receive_replayed(recording, started.elapsed());
+        }
+
+        let mut packets = Vec::new();
+        let mut buffer = [0; PACKET_SIZE_MAX];
+
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((length, address)) => {
+                    packets.push((address, buffer.get(..length).unwrap_or_default().to_vec()));
+                }
+                Err(error) => {
+                    if error.kind() == ErrorKind::WouldBlock {
+                        break;
+                    } else {
+                        log::error!("Failed to receive new packets. {}", error);
+                    }
+                }
+            }
+        }
+
+        if let Some(conditioner) = self.incoming_conditioner.as_mut() {
+            let now = Instant::now();
+
+            for (address, packet) in &packets {
+                conditioner.push(*address, packet, now);
+            }
+
+            return conditioner.take_due(now);
+        } else {
+            return packets;
+        }
+    }
+
+    /// Flushes queued messages, removes disconnected connections and returns them
+    pub fn update_connections(&mut self) -> Vec<(SocketAddr, NetConnection)> {
+        // A replay may pause for long, but it isn't lost until it's over
+        let is_replay = self.is_replay();
+        let is_replay_over = self.replay.as_ref().map_or(false, |(r, _)| r.is_empty());
+
+        for connection in self.connections.values_mut() {
+            if is_replay_over {
+                connection.session_token = None;
+                connection.disconnect("The replay is over".to_string());
+            } else if !is_replay && connection.last_received.elapsed() > self.config.timeout {
+                connection.disconnect("Timed out".to_string());
+            }
+        }
+
+        self.flush_connections();
 
+        let mut disconnected = Vec::new();
+
+        for (address, connection) in &self.connections {
             if let NetConnectionStatus::Disconnected(ref reason) = *connection.get_status() {
                 disconnected.push(*address);
-                log::warn!("{} disconnected. {}", address, reason);
+                log::warn!(
+                    "{} disconnected. {}. RTT: {:?}",
+                    address,
+                    reason,
+                    connection.get_rtt(),
+                );
             }
         }
 
-        for address in &disconnected {
-            self.connections.remove(address);
+        return disconnected
+            .into_iter()
+            .filter_map(|address| Some((address, self.connections.remove(&address)?)))
+            .collect();
+    }
+
+    /// Sends queued messages of every connection and records them. Nothing is sent while replaying
+    /// since there's no real peer
+    fn flush_connections(&mut self) {
+        for (address, connection) in &mut self.connections {
+            if self.replay.is_some() {
+                connection.discard_outgoing();
+                continue;
+            }
+
+            let packets = connection.flush(
+                &self.socket,
+                &mut self.outgoing_conditioner,
+                address,
+                self.config.heartbeat_interval,
+            );
+
+            if let Some(recorder) = self.recorder.as_mut() {
+                for packet in &packets {
+                    recorder.record_packet(NetDirection::Outgoing, *address, packet.as_bytes());
+                }
+            }
+        }
+
+        self.send_conditioned();
+
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.flush();
+        }
+    }
+
+    /// Sends packets held by the conditioner once they are due
+    fn send_conditioned(&mut self) {
+        if let Some(conditioner) = self.outgoing_conditioner.as_mut() {
+            for (address, packet) in conditioner.take_due(Instant::now()) {
+                if let Err(error) = self.socket.send_to(&packet, address) {
+                    log::warn!("Failed to send a packet to {}: {}", address, error);
+                }
+            }
         }
     }
 
     pub fn send_to(&mut self, address: &SocketAddr, mut message: Message) {
         if let Some(connection) = self.connections.get_mut(address) {
-            connection.send(&self.socket, address, &mut message);
+            connection.send(&mut message);
         }
     }
 
     pub fn send_to_all(&mut self, mut message: Message) {
-        for (address, connection) in &mut self.connections {
-            connection.send(&self.socket, address, &mut message);
+        for connection in self.connections.values_mut() {
+            connection.send(&mut message);
         }
     }
 
-    /// A faster way to send a message by skipping acknowledgement and error handling.
-    pub fn send_to_all_unreliably(&self, message: &Message) {
+    /// Sends the message to clients which the entity is spawned on
+    pub fn send_to_relevant(&mut self, entity: Entity, mut message: Message) {
+        for connection in self.connections.values_mut() {
+            if connection.is_relevant(entity) {
+                connection.send(&mut message);
+            }
+        }
+    }
+
+    /// A faster way to send a message by skipping acknowledgement.
+    pub fn send_to_all_unreliably(&mut self, message: &Message) {
         let encoded = message.encode();
 
-        #[allow(unused_must_use)]
-        for address in self.connections.keys() {
-            send(&self.socket, address, &encoded);
+        for connection in self.connections.values_mut() {
+            connection.send_unreliably(encoded.clone());
+        }
+    }
+
+    /// Tells peers that this side leaves the game. The message is sent once without waiting for
+    /// acknowledgement, so peers which miss it will drop the connection by timeout
+    pub fn leave(&mut self, reason: &str) {
+        let message;
+
+        if self.is_server {
+            message = Message::ServerShutdown {
+                id: 0,
+                reason: reason.to_string(),
+            };
+        } else {
+            message = Message::Leave {
+                id: 0,
+                reason: reason.to_string(),
+            };
+        }
+
+        self.send_to_all(message);
+        self.flush_connections();
+
+        for connection in self.connections.values_mut() {
+            connection.session_token = None;
+            connection.disconnect(reason.to_string());
+        }
+    }
+
+    /// Tells the client why it's disconnected and drops it without keeping its session
+    pub fn kick(&mut self, address: &SocketAddr, reason: &str) {
+        self.send_to(
+            address,
+            Message::Kick {
+                id: 0,
+                reason: reason.to_string(),
+            },
+        );
+
+        self.drop_connection(address, format!("Kicked: {}", reason));
+    }
+
+    /// Tells the client why it can't join and drops it
+    pub fn reject(&mut self, address: &SocketAddr, reason: &str) {
+        self.send_to(
+            address,
+            Message::JoinReject {
+                id: 0,
+                reason: reason.to_string(),
+            },
+        );
+
+        self.drop_connection(address, format!("Rejected: {}", reason));
+    }
+
+    /// Sends the queued messages once and disconnects without keeping the session
+    fn drop_connection(&mut self, address: &SocketAddr, reason: String) {
+        self.flush_connections();
+
+        if let Some(connection) = self.connections.get_mut(address) {
+            connection.session_token = None;
+            connection.disconnect(reason);
+        }
+    }
+
+    /// Counts the attempt of a new client to join. Returns whether it may connect
+    pub fn try_join(&mut self, address: &SocketAddr) -> bool {
+        return self.join_limiter.try_join(address.ip(), Instant::now());
+    }
+
+    /// Kicks everyone connected from the address and doesn't let it join again. The error tells
+    /// the ban hasn't been saved, but it's still kept until restart
+    pub fn ban(&mut self, address: IpAddr, reason: &str) -> Result<(), String> {
+        let banned = self
+            .connections
+            .keys()
+            .filter(|a| a.ip() == address)
+            .copied()
+            .collect::<Vec<SocketAddr>>();
+
+        for banned in &banned {
+            self.kick(banned, reason);
+        }
+
+        return self.bans.add(address);
+    }
+
+    pub fn is_banned(&self, address: &SocketAddr) -> bool {
+        return self.bans.contains(address.ip());
+    }
+
+    /// Gives the connection a new session token to rejoin with later
+    pub fn start_session(&mut self, address: &SocketAddr) -> u64 {
+        let token = rand::random();
+
+        if let Some(connection) = self.connections.get_mut(address) {
+            connection.session_token = Some(token);
+        }
+
+        return token;
+    }
+
+    /// Keeps the entity of the dropped connection for a while unless the client has left on its
+    /// own. Returns whether it's kept
+    pub fn keep_session(&mut self, connection: &NetConnection) -> bool {
+        if let (Some(token), Some(entity)) = (connection.session_token, connection.attached_entity)
+        {
+            self.sessions.insert(
+                token,
+                NetSession {
+                    entity,
+                    is_spectator: connection.is_spectator,
+                    dropped: Instant::now(),
+                },
+            );
+
+            return true;
+        } else {
+            return false;
+        }
+    }
+
+    /// Attaches the entity of the session to the connection. The session may be dropped already
+    /// or still held by the old connection if the client has changed its address. Returns the
+    /// entity if the session is found
+    pub fn resume_session(&mut self, address: &SocketAddr, token: u64) -> Option<Entity> {
+        if !self.connections.contains_key(address) {
+            return None;
         }
+
+        let mut session = self.sessions.remove(&token);
+
+        for (old_address, connection) in &mut self.connections {
+            if old_address != address && connection.session_token == Some(token) {
+                if let Some(entity) = connection.attached_entity.take() {
+                    session = Some(NetSession {
+                        entity,
+                        is_spectator: connection.is_spectator,
+                        dropped: Instant::now(),
+                    });
+                }
+
+                connection.session_token = None;
+                connection.disconnect(format!("Rejoined from {}", address));
+            }
+        }
+
+        let session = session?;
+        let connection = self.connections.get_mut(address)?;
+        connection.attached_entity = Some(session.entity);
+        connection.is_spectator = session.is_spectator;
+        connection.session_token = Some(token);
+
+        return Some(session.entity);
+    }
+
+    /// Forgets sessions which haven't been resumed in time and returns their entities
+    pub fn take_expired_sessions(&mut self) -> Vec<Entity> {
+        let grace = self.config.session_grace;
+        let mut expired = Vec::new();
+
+        self.sessions.retain(|_, session| {
+            let is_expired = session.dropped.elapsed() > grace;
+
+            if is_expired {
+                expired.push(session.entity);
+            }
+
+            !is_expired
+        });
+
+        return expired;
     }
 
     pub fn attach_entity(&mut self, address: &SocketAddr, entity: Entity) {
@@ -110,20 +576,105 @@ impl NetResource {
         }
     }
 
+    /// Attaches the new entity wherever the old one is attached, including dropped sessions
+    pub fn replace_entity(&mut self, old: Entity, new: Entity) {
+        for connection in self.connections.values_mut() {
+            if connection.attached_entity == Some(old) {
+                connection.attached_entity = Some(new);
+            }
+        }
+
+        for session in self.sessions.values_mut() {
+            if session.entity == old {
+                session.entity = new;
+            }
+        }
+    }
+
+    /// The address of the client which the entity is attached to
+    pub fn find_address(&self, entity: Entity) -> Option<SocketAddr> {
+        return self
+            .connections
+            .iter()
+            .find(|(_, c)| c.attached_entity == Some(entity))
+            .map(|(address, _)| *address);
+    }
+
+    pub fn set_spectator(&mut self, address: &SocketAddr, is_spectator: bool) {
+        if let Some(connection) = self.connections.get_mut(address) {
+            connection.is_spectator = is_spectator;
+        }
+    }
+
+    /// Remembers the input applied to the entity to echo it to the client controlling it
+    pub fn acknowledge_input(&mut self, entity: Entity, input_sequence: u16) {
+        for connection in self.connections.values_mut() {
+            if connection.attached_entity == Some(entity) {
+                connection.applied_input = Some((input_sequence, Instant::now()));
+            }
+        }
+    }
+
+    /// How far back in time to check hits of projectiles fired by the entity, so the client
+    /// controlling it hits what it saw
+    pub fn get_rewind(&self, entity: Entity) -> Duration {
+        for connection in self.connections.values() {
+            if connection.attached_entity == Some(entity) {
+                // The client sees others interpolated towards the latest snapshot
+                let rewind = connection.get_rtt().unwrap_or_default() + POSITION_UPDATE_INTERVAL;
+                return rewind.min(self.config.rewind_max);
+            }
+        }
+
+        return Duration::from_millis(0);
+    }
+
+    pub const fn get_config(&self) -> &NetConfig {
+        return &self.config;
+    }
+
     pub const fn is_server(&self) -> bool {
         return self.is_server;
     }
+
+    pub const fn is_replay(&self) -> bool {
+        return self.replay.is_some();
+    }
 }
 
 impl NetConnection {
-    pub fn new() -> Self {
+    pub fn new(config: &NetConfig) -> Self {
         return Self {
             status: NetConnectionStatus::Connected,
             unacknowledged_messages: HashMap::new(),
+            unacknowledged_messages_max: config.unacknowledged_messages_max,
+            unreliable_messages: Vec::new(),
             held_messages: HashMap::new(),
+            held_messages_max: config.held_messages_max,
+            overflow_policy: config.overflow_policy,
+            discarded_messages: 0,
+            deferred_messages: 0,
+            resent_messages: 0,
+            packets_sent: 0,
+            packets_received: 0,
+            bytes_sent: 0,
+            bytes_received: 0,
             next_incoming_message_id: 0,
             next_outgoing_message_id: 0,
+            is_ack_pending: false,
+            last_received: Instant::now(),
+            last_sent: Instant::now(),
+            rtt: RttEstimator::new(),
+            acknowledged_snapshot: None,
+            applied_input: None,
+            chat_messages_max: config.chat_messages_max,
+            chat_window: config.chat_window,
+            chat_messages: (Instant::now(), 0),
+            relevant_entities: HashSet::new(),
             attached_entity: None,
+            is_spectator: false,
+            session_token: None,
+            join_details: None,
         };
     }
 
@@ -133,54 +684,191 @@ impl NetConnection {
         return id;
     }
 
-    pub fn send(&mut self, socket: &UdpSocket, address: &SocketAddr, message: &mut Message) {
+    /// Sends the details held since joining, once the server has let the client proceed
+    pub fn proceed_join(&mut self) {
+        if let Some(mut details) = self.join_details.take() {
+            self.send(&mut details);
+        }
+    }
+
+    /// Queues the message to be sent reliably on the next flush. Disconnects if the message
+    /// doesn't fit a packet, since it would never be delivered and the peer would hold every
+    /// message after it
+    pub fn send(&mut self, message: &mut Message) {
+        if !self.is_connected() {
+            return;
+        }
+
+        if !message.has_id() {
+            self.send_unreliably(message.encode());
+            return;
+        }
+
+        if self.unacknowledged_messages.len() >= self.unacknowledged_messages_max {
+            self.on_overflow(message);
+            return;
+        }
+
+        let id = self.generate_message_id();
+        message.set_id(id);
+
+        let data = message.encode();
+
+        if data.len() > MESSAGE_SIZE_MAX {
+            log::error!("A message of {} bytes doesn't fit a packet", data.len());
+            self.disconnect(format!("Too large message of {} bytes", data.len()));
+            return;
+        }
+
+        self.unacknowledged_messages.insert(
+            id,
+            UnacknowledgedMessage {
+                data,
+                last_sent: None,
+                resends: 0,
+            },
+        );
+    }
+
+    /// Queues the encoded message to be sent once on the next flush
+    pub fn send_unreliably(&mut self, encoded: Vec<u8>) {
         if self.is_connected() {
-            let id;
+            self.unreliable_messages.push(encoded);
+        }
+    }
 
-            if message.has_id() {
-                let generated_id = self.generate_message_id();
-                message.set_id(generated_id);
-                id = Some(generated_id);
-            } else {
-                id = None;
+    fn on_overflow(&mut self, message: &Message) {
+        if message.is_droppable() && self.overflow_policy == NetOverflowPolicy::DiscardDroppable {
+            self.discarded_messages += 1;
+        } else {
+            self.disconnect("Too many unacknowledged messages".to_string());
+        }
+    }
+
+    /// Packs new and timed out reliable messages, unreliable messages and acknowledgements into as
+    /// few packets as possible and sends them. An empty packet is sent if there was nothing to
+    /// send for the heartbeat interval, so the peer knows the connection is alive. Returns the sent
+    /// packets
+    pub fn flush(
+        &mut self,
+        socket: &UdpSocket,
+        conditioner: &mut Option<NetConditioner>,
+        address: &SocketAddr,
+        heartbeat_interval: Duration,
+    ) -> Vec<Packet> {
+        if !self.is_connected() {
+            return Vec::new();
+        }
+
+        let now = Instant::now();
+        let header = self.create_header();
+        let mut packets = Vec::new();
+        let mut packet = Packet::new(header);
+
+        for id in self.get_due_message_ids() {
+            if let Some(message) = self.unacknowledged_messages.get_mut(&id) {
+                if message.last_sent.is_some() {
+                    message.resends = message.resends.saturating_add(1);
+                    self.resent_messages += 1;
+                }
+
+                message.last_sent = Some(now);
+                pack(&mut packets, &mut packet, header, &message.data);
             }
+        }
 
-            let encoded = message.encode();
+        for message in self.unreliable_messages.take_content() {
+            pack(&mut packets, &mut packet, header, &message);
+        }
 
-            if let Err(error) = send(socket, address, &encoded) {
-                self.disconnect(error);
-            } else if let Some(id) = id {
-                self.unacknowledged_messages.insert(
-                    id,
-                    UnacknowledgedMessage {
-                        data: encoded,
-                        last_sent: Instant::now(),
-                    },
-                );
+        let is_heartbeat = self.last_sent.elapsed() > heartbeat_interval;
+
+        if !packet.is_empty() || (packets.is_empty() && (self.is_ack_pending || is_heartbeat)) {
+            packets.push(packet);
+        }
+
+        for packet in &packets {
+            match send(socket, conditioner, address, packet.as_bytes()) {
+                Ok(size) => {
+                    self.packets_sent += 1;
+                    self.bytes_sent += size as u64;
+                    self.last_sent = now;
+                }
+                Err(error) => {
+                    self.disconnect(error);
+                    return Vec::new();
+                }
             }
         }
+
+        self.is_ack_pending = false;
+
+        return packets;
     }
 
-    pub fn resend_unacknowledged_messages(&mut self, socket: &UdpSocket, address: &SocketAddr) {
-        if self.is_connected() {
-            for message in self.unacknowledged_messages.values_mut() {
-                if message.last_sent.elapsed() > MESSAGE_RESEND_INTERVAL {
-                    message.last_sent = Instant::now();
+    /// Drops queued messages without sending them
+    pub fn discard_outgoing(&mut self) {
+        self.unacknowledged_messages.clear();
+        self.unreliable_messages.clear();
+        self.is_ack_pending = false;
+    }
 
-                    if let Err(error) = send(socket, address, &message.data) {
-                        self.disconnect(error);
-                        break;
-                    }
-                }
+    /// Returns IDs of messages which haven't been sent yet or have timed out, in sending order
+    fn get_due_message_ids(&self) -> Vec<u16> {
+        let mut ids = self
+            .unacknowledged_messages
+            .iter()
+            .filter(|(_, m)| {
+                m.last_sent
+                    .map_or(true, |t| t.elapsed() > self.rtt.get_timeout(m.resends))
+            })
+            .map(|(id, _)| *id)
+            .collect::<Vec<u16>>();
+
+        ids.sort_by(|a, b| a.cmp_sequence(*b));
+
+        return ids;
+    }
+
+    fn create_header(&self) -> PacketHeader {
+        let mut ack_bits = 0;
+
+        for i in 0..ACK_BITS_COUNT {
+            let id = self.next_incoming_message_id.wrapping_add(i + 1);
+
+            if self.held_messages.contains_key(&id) {
+                ack_bits |= 1 << i;
             }
         }
+
+        return PacketHeader {
+            ack: self.next_incoming_message_id,
+            ack_bits,
+        };
+    }
+
+    pub fn mark_received(&mut self, size: usize) {
+        self.last_received = Instant::now();
+        self.packets_received += 1;
+        self.bytes_received += size as u64;
     }
 
+    /// Returns the message if it's the next one or has no ID. Early messages are held until the
+    /// missing ones arrive unless there're too many held, then they aren't acknowledged so the
+    /// peer resends them later
     pub fn filter_message(&mut self, message: Message) -> Option<Message> {
         if let Some(id) = message.get_id() {
-            match id.cmp(&self.next_incoming_message_id) {
+            // Acknowledge duplicates too since the peer may have missed previous acknowledgement
+            self.is_ack_pending = true;
+
+            match id.cmp_sequence(self.next_incoming_message_id) {
                 Ordering::Greater => {
-                    self.held_messages.insert(id, message);
+                    if self.held_messages.len() < self.held_messages_max {
+                        self.held_messages.insert(id, message);
+                    } else if !self.held_messages.contains_key(&id) {
+                        self.deferred_messages += 1;
+                    }
+
                     return None;
                 }
                 Ordering::Less => {
@@ -207,23 +895,104 @@ impl NetConnection {
         return messages;
     }
 
-    pub fn acknowledge_message(&mut self, id: u16) {
-        if self.unacknowledged_messages.remove(&id).is_none() {
-            log::warn!(
-                "Got response for {} message but it was not an unacknowledged message",
-                id,
-            );
+    /// Forgets messages acknowledged by the peer's packet header
+    pub fn acknowledge_messages(&mut self, header: PacketHeader) {
+        let rtt = &mut self.rtt;
+
+        self.unacknowledged_messages.retain(|id, message| {
+            let is_acknowledged;
+
+            if id.cmp_sequence(header.ack) == Ordering::Less {
+                is_acknowledged = true;
+            } else {
+                let bit = id.wrapping_sub(header.ack).wrapping_sub(1);
+                is_acknowledged = bit < ACK_BITS_COUNT && header.ack_bits & (1 << bit) != 0;
+            }
+
+            // It's unknown which copy of a resent message is acknowledged, so skip such ones
+            if is_acknowledged && message.resends == 0 {
+                if let Some(last_sent) = message.last_sent {
+                    rtt.add_sample(last_sent.elapsed());
+                }
+            }
+
+            return !is_acknowledged;
+        });
+    }
+
+    pub fn acknowledge_snapshot(&mut self, tick: u16) {
+        if self
+            .acknowledged_snapshot
+            .map_or(true, |t| tick.cmp_sequence(t) == Ordering::Greater)
+        {
+            self.acknowledged_snapshot = Some(tick);
         }
     }
 
+    /// The latest snapshot tick the peer has applied
+    pub const fn get_acknowledged_snapshot(&self) -> Option<u16> {
+        return self.acknowledged_snapshot;
+    }
+
+    pub fn is_relevant(&self, entity: Entity) -> bool {
+        return self.relevant_entities.contains(&entity);
+    }
+
+    /// Replaces entities spawned on the client and returns the previous ones
+    pub fn replace_relevant_entities(&mut self, entities: HashSet<Entity>) -> HashSet<Entity> {
+        return std::mem::replace(&mut self.relevant_entities, entities);
+    }
+
+    /// Counts a chat message of the client and returns whether it may be relayed
+    pub fn try_chat(&mut self, now: Instant) -> bool {
+        let (started, count) = &mut self.chat_messages;
+
+        if now.duration_since(*started) >= self.chat_window {
+            *started = now;
+            *count = 0;
+        }
+
+        *count = count.saturating_add(1);
+
+        return *count <= self.chat_messages_max;
+    }
+
+    pub fn get_input_echo(&self) -> Option<InputEcho> {
+        return self.applied_input.map(|(sequence, applied)| InputEcho {
+            sequence,
+            age: u16::try_from(applied.elapsed().as_millis()).unwrap_or(u16::MAX),
+        });
+    }
+
     pub fn disconnect(&mut self, reason: String) {
         if self.is_connected() {
             self.unacknowledged_messages = HashMap::new();
+            self.unreliable_messages = Vec::new();
             self.held_messages = HashMap::new();
             self.status = NetConnectionStatus::Disconnected(reason);
         }
     }
 
+    /// Smoothed round-trip time, unknown until the first acknowledgement
+    pub const fn get_rtt(&self) -> Option<Duration> {
+        return self.rtt.get_rtt();
+    }
+
+    pub fn get_metrics(&self) -> NetMetrics {
+        return NetMetrics {
+            rtt: self.get_rtt(),
+            unacknowledged: self.unacknowledged_messages.len(),
+            held: self.held_messages.len(),
+            discarded: self.discarded_messages,
+            deferred: self.deferred_messages,
+            resent: self.resent_messages,
+            packets_sent: self.packets_sent,
+            packets_received: self.packets_received,
+            bytes_sent: self.bytes_sent,
+            bytes_received: self.bytes_received,
+        };
+    }
+
     pub const fn get_status(&self) -> &NetConnectionStatus {
         return &self.status;
     }
@@ -236,8 +1005,246 @@ impl NetConnection {
     }
 }
 
-fn send(socket: &UdpSocket, address: &SocketAddr, message: &[u8]) -> Result<usize, String> {
+fn bind_socket(address: &str) -> Result<UdpSocket, String> {
+    let socket = UdpSocket::bind(address).map_err(|e| format!("{}", e))?;
+    socket.set_nonblocking(true).map_err(|e| format!("{}", e))?;
+    return Ok(socket);
+}
+
+/// Adds the message to the packet. Moves the packet to the full ones and starts a new one if
+/// there's no room left
+fn pack(packets: &mut Vec<Packet>, packet: &mut Packet, header: PacketHeader, message: &[u8]) {
+    if packet.push(message) {
+        return;
+    }
+
+    if !packet.is_empty() {
+        packets.push(std::mem::replace(packet, Packet::new(header)));
+    }
+
+    if !packet.push(message) {
+        log::error!("A message of {} bytes doesn't fit a packet", message.len());
+    }
+}
+
+/// Wraps every due recorded incoming message into a packet of its own
+fn receive_replayed(recording: &mut NetRecording, time: Duration) -> Vec<(SocketAddr, Vec<u8>)> {
+    let mut packets = Vec::new();
+
+    for record in recording.take_due(time) {
+        if record.direction == NetDirection::Incoming {
+            let mut packet = Packet::new(PacketHeader {
+                ack: 0,
+                ack_bits: 0,
+            });
+            packet.push(&record.message.encode());
+            packets.push((record.address, packet.as_bytes().to_vec()));
+        }
+    }
+
+    return packets;
+}
+
+fn send(
+    socket: &UdpSocket,
+    conditioner: &mut Option<NetConditioner>,
+    address: &SocketAddr,
+    message: &[u8],
+) -> Result<usize, String> {
+    if let Some(conditioner) = conditioner.as_mut() {
+        conditioner.push(*address, message, Instant::now());
+        return Ok(message.len());
+    }
+
     return socket
         .send_to(message, address)
         .map_err(|e| format!("{}", e));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: u16) -> Message {
+        return Message::JoinAccept {
+            id,
+            session_token: 0,
+        };
+    }
+
+    fn filter(connection: &mut NetConnection, id: u16) -> Option<u16> {
+        return connection
+            .filter_message(message(id))
+            .and_then(|m| m.get_id());
+    }
+
+    fn take_held(connection: &mut NetConnection) -> Vec<u16> {
+        return connection
+            .take_next_held_messages()
+            .iter()
+            .filter_map(Message::get_id)
+            .collect();
+    }
+
+    #[test]
+    fn test_filter_message_across_wrap() {
+        let mut connection = NetConnection::new(&NetConfig::default());
+        connection.next_incoming_message_id = u16::MAX - 1;
+
+        assert_eq!(None, filter(&mut connection, u16::MAX), "Held as early");
+        assert_eq!(None, filter(&mut connection, 1), "Held as early after wrap");
+        assert_eq!(Some(u16::MAX - 1), filter(&mut connection, u16::MAX - 1));
+        assert_eq!(vec![u16::MAX], take_held(&mut connection));
+        assert_eq!(Some(0), filter(&mut connection, 0));
+        assert_eq!(vec![1], take_held(&mut connection));
+        assert_eq!(2, connection.next_incoming_message_id);
+
+        assert_eq!(None, filter(&mut connection, u16::MAX), "Duplicate");
+        assert_eq!(None, filter(&mut connection, 1), "Duplicate");
+        assert!(connection.held_messages.is_empty(), "Duplicates not held");
+    }
+
+    #[test]
+    fn test_acknowledge_messages_across_wrap() {
+        let mut connection = NetConnection::new(&NetConfig::default());
+        connection.next_outgoing_message_id = u16::MAX;
+
+        for _ in 0..3 {
+            connection.send(&mut message(0));
+        }
+
+        connection.acknowledge_messages(PacketHeader {
+            ack: 0,
+            ack_bits: 0b1,
+        });
+
+        let ids = connection
+            .unacknowledged_messages
+            .keys()
+            .copied()
+            .collect::<Vec<u16>>();
+
+        assert_eq!(vec![0], ids, "Acknowledged before 0 and 1 selectively");
+
+        connection.acknowledge_messages(PacketHeader {
+            ack: 2,
+            ack_bits: 0,
+        });
+
+        assert!(connection.unacknowledged_messages.is_empty());
+    }
+
+    #[test]
+    fn test_flush() -> Result<(), Box<dyn std::error::Error>> {
+        let socket = UdpSocket::bind("127.0.0.1:0")?;
+        let address = socket.local_addr()?;
+        let mut connection = NetConnection::new(&NetConfig::default());
+        connection.filter_message(message(1));
+
+        for _ in 0..3 {
+            connection.send(&mut message(0));
+        }
+
+        connection.send_unreliably(Message::SnapshotAck { tick: 0 }.encode());
+
+        let packets = connection.flush(&socket, &mut None, &address, Duration::from_secs(1));
+        assert_eq!(1, packets.len());
+
+        let mut buffer = [0; PACKET_SIZE_MAX];
+        let length = socket.recv(&mut buffer)?;
+        let (header, messages) = Packet::decode(buffer.get(..length).unwrap_or_default())?;
+        let ids = messages.iter().map(Message::get_id).collect::<Vec<_>>();
+
+        assert_eq!(0, header.ack);
+        assert_eq!(0b1, header.ack_bits, "Held message");
+        assert_eq!(
+            vec![Some(0), Some(1), Some(2), None],
+            ids,
+            "All in a single packet"
+        );
+
+        let metrics = connection.get_metrics();
+        assert_eq!(1, metrics.packets_sent);
+        assert_eq!(length as u64, metrics.bytes_sent);
+        assert_eq!(3, metrics.unacknowledged);
+        assert_eq!(0, metrics.resent);
+
+        return Ok(());
+    }
+
+    #[test]
+    fn test_overflow() {
+        let config = NetConfig {
+            unacknowledged_messages_max: 2,
+            held_messages_max: 1,
+            overflow_policy: NetOverflowPolicy::DiscardDroppable,
+            ..NetConfig::default()
+        };
+
+        let mut connection = NetConnection::new(&config);
+        assert_eq!(None, filter(&mut connection, 5), "Held");
+        assert_eq!(None, filter(&mut connection, 6), "Held are full");
+        assert_eq!(Some(0), filter(&mut connection, 0), "Expected is taken");
+
+        let mut droppable = Message::ClientInputDirection {
+            id: 0,
+            direction: 0.0,
+        };
+
+        connection.send(&mut message(0));
+        connection.send(&mut message(0));
+        connection.send(&mut droppable);
+        assert!(connection.is_connected(), "Droppable is discarded");
+
+        let metrics = connection.get_metrics();
+        assert_eq!(2, metrics.unacknowledged);
+        assert_eq!(1, metrics.held);
+        assert_eq!(1, metrics.discarded);
+        assert_eq!(1, metrics.deferred);
+
+        connection.send(&mut message(0));
+        assert!(!connection.is_connected(), "Disconnected on not droppable");
+
+        let mut connection = NetConnection::new(&NetConfig {
+            overflow_policy: NetOverflowPolicy::Disconnect,
+            ..config
+        });
+
+        connection.send(&mut message(0));
+        connection.send(&mut message(0));
+        connection.send(&mut droppable);
+        assert!(!connection.is_connected(), "Disconnected on droppable");
+    }
+
+    #[test]
+    fn test_try_chat() {
+        let mut connection = NetConnection::new(&NetConfig {
+            chat_messages_max: 2,
+            chat_window: Duration::from_secs(5),
+            ..NetConfig::default()
+        });
+
+        let now = Instant::now();
+
+        assert!(connection.try_chat(now));
+        assert!(connection.try_chat(now + Duration::from_secs(1)));
+        assert!(!connection.try_chat(now + Duration::from_secs(2)), "Flood");
+        assert!(
+            connection.try_chat(now + Duration::from_secs(5)),
+            "The window is over",
+        );
+    }
+
+    #[test]
+    fn test_send_too_large() {
+        let mut connection = NetConnection::new(&NetConfig::default());
+
+        connection.send(&mut Message::JoinReject {
+            id: 0,
+            reason: "-".repeat(MESSAGE_SIZE_MAX),
+        });
+
+        assert!(!connection.is_connected());
+        assert_eq!(0, connection.get_metrics().unacknowledged, "Not queued");
+    }
+}
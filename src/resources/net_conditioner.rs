@@ -0,0 +1,131 @@
+use crate::models::NetConditionerConfig;
+use rand::Rng;
+use rand::SeedableRng;
+use rand_pcg::Pcg32;
+use std::net::SocketAddr;
+use std::time::Duration;
+use std::time::Instant;
+
+/// How much longer than others a reordered packet is held
+const REORDERING_DELAY: Duration = Duration::from_millis(20);
+
+/// Simulates a bad network by dropping, duplicating, delaying and reordering packets
+pub struct NetConditioner {
+    config: NetConditionerConfig,
+    randomizer: Pcg32,
+    packets: Vec<(Instant, SocketAddr, Vec<u8>)>,
+}
+
+impl NetConditioner {
+    pub fn new(config: NetConditionerConfig, seed: u64) -> Self {
+        return Self {
+            config,
+            randomizer: Pcg32::seed_from_u64(seed),
+            packets: Vec::new(),
+        };
+    }
+
+    pub fn push(&mut self, address: SocketAddr, packet: &[u8], now: Instant) {
+        if self.gen_chance(self.config.loss) {
+            return;
+        }
+
+        let copies;
+
+        if self.gen_chance(self.config.duplication) {
+            copies = 2;
+        } else {
+            copies = 1;
+        }
+
+        for _ in 0..copies {
+            let mut delay = self.config.latency + self.config.jitter.mul_f64(self.randomizer.gen());
+
+            if self.gen_chance(self.config.reordering) {
+                delay += REORDERING_DELAY;
+            }
+
+            self.packets.push((now + delay, address, packet.to_vec()));
+        }
+    }
+
+    /// Takes packets which are due by the moment, in order of their due time
+    pub fn take_due(&mut self, now: Instant) -> Vec<(SocketAddr, Vec<u8>)> {
+        self.packets.sort_by_key(|(due, ..)| *due);
+
+        let count = self
+            .packets
+            .iter()
+            .take_while(|(due, ..)| *due <= now)
+            .count();
+
+        return self
+            .packets
+            .drain(..count)
+            .map(|(_, address, packet)| (address, packet))
+            .collect();
+    }
+
+    fn gen_chance(&mut self, probability: f64) -> bool {
+        return self.randomizer.gen::<f64>() < probability;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn transfer(config: NetConditionerConfig) -> Vec<u8> {
+        let address = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 2003);
+        let now = Instant::now();
+        let mut conditioner = NetConditioner::new(config, config.seed);
+
+        for i in 0..100 {
+            conditioner.push(address, &[i], now);
+        }
+
+        return conditioner
+            .take_due(now + Duration::from_secs(1))
+            .iter()
+            .filter_map(|(_, packet)| packet.first().copied())
+            .collect();
+    }
+
+    #[test]
+    fn test_loss() {
+        let config = NetConditionerConfig {
+            loss: 0.5,
+            seed: 1,
+            ..NetConditionerConfig::default()
+        };
+
+        let received = transfer(config);
+        assert!(received.len() > 20 && received.len() < 80);
+        assert_eq!(received, transfer(config), "Deterministic");
+    }
+
+    #[test]
+    fn test_duplication() {
+        let received = transfer(NetConditionerConfig {
+            duplication: 1.0,
+            ..NetConditionerConfig::default()
+        });
+
+        assert_eq!(200, received.len());
+    }
+
+    #[test]
+    fn test_reordering() {
+        let mut received = transfer(NetConditionerConfig {
+            latency: Duration::from_millis(50),
+            reordering: 0.5,
+            ..NetConditionerConfig::default()
+        });
+
+        assert_eq!(100, received.len());
+        assert!(received.windows(2).any(|w| w.first() > w.last()));
+        received.sort_unstable();
+        assert_eq!((0..100).collect::<Vec<u8>>(), received);
+    }
+}
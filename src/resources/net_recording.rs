@@ -0,0 +1,240 @@
+use crate::resources::Message;
+use crate::resources::Packet;
+use crate::resources::PROTOCOL_VERSION;
+use bincode::Options;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Starts every recording file, followed by the protocol version
+const MAGIC: &[u8; 4] = b"SHNR";
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Debug)]
+pub enum NetDirection {
+    Incoming,
+    Outgoing,
+}
+
+/// Precedes every encoded message in a recording file
+#[derive(Serialize, Deserialize)]
+struct RecordHeader {
+    /// Milliseconds since the recording has started
+    time: u64,
+    direction: NetDirection,
+    address: SocketAddr,
+}
+
+/// Writes every sent and received message to a file
+pub struct NetRecorder {
+    file: BufWriter<File>,
+    started: Instant,
+}
+
+pub struct NetRecord {
+    pub time: Duration,
+    pub direction: NetDirection,
+    pub address: SocketAddr,
+    pub message: Message,
+}
+
+/// A recorded session, oldest messages first
+pub struct NetRecording {
+    records: VecDeque<NetRecord>,
+}
+
+impl NetRecorder {
+    /// Creates the file or overwrites it if it exists
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("{}", e))?;
+        let mut file = BufWriter::new(file);
+
+        file.write_all(MAGIC).map_err(|e| format!("{}", e))?;
+        options()
+            .serialize_into(&mut file, &PROTOCOL_VERSION)
+            .map_err(|e| format!("{}", e))?;
+
+        return Ok(Self {
+            file,
+            started: Instant::now(),
+        });
+    }
+
+    /// Records every message of the packet. Packets which can't be decoded are skipped
+    pub fn record_packet(&mut self, direction: NetDirection, address: SocketAddr, packet: &[u8]) {
+        if let Ok((_, messages)) = Packet::decode(packet) {
+            for message in &messages {
+                self.record(direction, address, message);
+            }
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn record(&mut self, direction: NetDirection, address: SocketAddr, message: &Message) {
+        let header = RecordHeader {
+            time: self.started.elapsed().as_millis() as u64,
+            direction,
+            address,
+        };
+
+        let result = options()
+            .serialize_into(&mut self.file, &header)
+            .map_err(|e| format!("{}", e))
+            .and_then(|_| {
+                self.file
+                    .write_all(&message.encode())
+                    .map_err(|e| format!("{}", e))
+            });
+
+        if let Err(error) = result {
+            log::error!("Failed to record a message: {}", error);
+        }
+    }
+
+    pub fn flush(&mut self) {
+        if let Err(error) = self.file.flush() {
+            log::error!("Failed to flush the recording: {}", error);
+        }
+    }
+}
+
+impl NetRecording {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|e| format!("{}", e))?;
+        let mut data = data.as_slice();
+
+        if !data.starts_with(MAGIC) {
+            return Err("Not a network recording".to_string());
+        }
+
+        data = data.get(MAGIC.len()..).unwrap_or_default();
+
+        let protocol_version: u16 = options()
+            .deserialize_from(&mut data)
+            .map_err(|e| format!("{}", e))?;
+
+        if protocol_version != PROTOCOL_VERSION {
+            return Err(format!(
+                "The recording has protocol version {}, but the game has {}",
+                protocol_version, PROTOCOL_VERSION,
+            ));
+        }
+
+        let mut records = VecDeque::new();
+
+        while !data.is_empty() {
+            let header: RecordHeader = options()
+                .deserialize_from(&mut data)
+                .map_err(|e| format!("{}", e))?;
+
+            let message = Message::decode_from(&mut data).map_err(|e| format!("{}", e))?;
+
+            records.push_back(NetRecord {
+                time: Duration::from_millis(header.time),
+                direction: header.direction,
+                address: header.address,
+                message,
+            });
+        }
+
+        return Ok(Self { records });
+    }
+
+    /// The peer which the first message has been received from
+    pub fn get_server_address(&self) -> Option<SocketAddr> {
+        return self
+            .records
+            .iter()
+            .find(|r| r.direction == NetDirection::Incoming)
+            .map(|r| r.address);
+    }
+
+    /// Takes records which have been made before the time since the recording start
+    pub fn take_due(&mut self, time: Duration) -> Vec<NetRecord> {
+        let mut due = Vec::new();
+
+        while self.records.front().map_or(false, |r| r.time <= time) {
+            if let Some(record) = self.records.pop_front() {
+                due.push(record);
+            }
+        }
+
+        return due;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.records.is_empty();
+    }
+}
+
+fn options() -> impl Options {
+    return bincode::DefaultOptions::new().with_varint_encoding();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::PacketHeader;
+
+    #[test]
+    fn test_record_load() -> Result<(), Box<dyn std::error::Error>> {
+        let path = std::env::temp_dir().join(format!("shooter_{}.rec", std::process::id()));
+        let client: SocketAddr = "127.0.0.1:3000".parse()?;
+        let server: SocketAddr = "127.0.0.1:2003".parse()?;
+
+        let mut packet = Packet::new(PacketHeader {
+            ack: 0,
+            ack_bits: 0,
+        });
+        packet.push(
+            &Message::JoinAccept {
+                id: 0,
+                session_token: 0,
+            }
+            .encode(),
+        );
+        packet.push(&Message::SnapshotAck { tick: 7 }.encode());
+
+        let mut recorder = NetRecorder::create(&path)?;
+        recorder.record(
+            NetDirection::Outgoing,
+            server,
+            &Message::SnapshotAck { tick: 1 },
+        );
+        recorder.record_packet(NetDirection::Incoming, server, packet.as_bytes());
+        recorder.record_packet(NetDirection::Incoming, client, b"garbage");
+        recorder.flush();
+
+        let mut recording = NetRecording::load(&path)?;
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(Some(server), recording.get_server_address());
+
+        let records = recording.take_due(Duration::from_secs(60));
+        let directions = records.iter().map(|r| r.direction).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                NetDirection::Outgoing,
+                NetDirection::Incoming,
+                NetDirection::Incoming,
+            ],
+            directions,
+            "Undecodable packets are skipped",
+        );
+
+        assert!(matches!(
+            records.get(2).map(|r| &r.message),
+            Some(Message::SnapshotAck { tick: 7 }),
+        ));
+
+        assert!(recording.is_empty());
+
+        return Ok(());
+    }
+}
@@ -0,0 +1,146 @@
+use crate::resources::Message;
+use bincode::Options;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Leaves room for IP and UDP headers within the common MTU
+pub const PACKET_SIZE_MAX: usize = 1200;
+
+/// A header with both fields taking the most varint bytes
+pub const PACKET_HEADER_SIZE_MAX: usize = 8;
+
+/// The largest encoded message which is sure to fit a packet
+pub const MESSAGE_SIZE_MAX: usize = PACKET_SIZE_MAX - PACKET_HEADER_SIZE_MAX;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct PacketHeader {
+    /// Every message with ID before it has been received
+    pub ack: u16,
+    /// Bit N means the message with `ack + 1 + N` ID has been received too
+    pub ack_bits: u32,
+}
+
+/// A datagram of a header followed by as many encoded messages as fit
+pub struct Packet {
+    data: Vec<u8>,
+    header_size: usize,
+}
+
+impl Packet {
+    pub fn new(header: PacketHeader) -> Self {
+        // I use unwrap here since I suppose there's nothing to worry about
+        #[allow(clippy::unwrap_used)]
+        let data = options().serialize(&header).unwrap();
+
+        return Self {
+            header_size: data.len(),
+            data,
+        };
+    }
+
+    /// Returns `false` if there's no room left for the message
+    pub fn push(&mut self, message: &[u8]) -> bool {
+        if self.data.len() + message.len() > PACKET_SIZE_MAX {
+            return false;
+        } else {
+            self.data.extend_from_slice(message);
+            return true;
+        }
+    }
+
+    pub fn decode(mut encoded: &[u8]) -> Result<(PacketHeader, Vec<Message>), bincode::Error> {
+        let header: PacketHeader = options()
+            .allow_trailing_bytes()
+            .deserialize_from(&mut encoded)?;
+
+        let mut messages = Vec::new();
+
+        while !encoded.is_empty() {
+            messages.push(Message::decode_from(&mut encoded)?);
+        }
+
+        return Ok((header, messages));
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        return &self.data;
+    }
+
+    /// Whether the packet has no messages, but it still carries the header
+    pub fn is_empty(&self) -> bool {
+        return self.data.len() == self.header_size;
+    }
+}
+
+fn options() -> impl Options {
+    return bincode::DefaultOptions::new().with_varint_encoding();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() -> Result<(), bincode::Error> {
+        let header = PacketHeader {
+            ack: u16::MAX,
+            ack_bits: 0b101,
+        };
+
+        let mut packet = Packet::new(header);
+        assert!(packet.is_empty());
+
+        for id in 0..3 {
+            assert!(packet.push(
+                &Message::JoinAccept {
+                    id,
+                    session_token: 0,
+                }
+                .encode()
+            ));
+        }
+
+        assert!(!packet.is_empty());
+
+        let (decoded_header, messages) = Packet::decode(packet.as_bytes())?;
+        let ids = messages
+            .iter()
+            .filter_map(Message::get_id)
+            .collect::<Vec<u16>>();
+        assert_eq!(header, decoded_header);
+        assert_eq!(vec![0, 1, 2], ids);
+
+        return Ok(());
+    }
+
+    #[test]
+    fn test_push_limit() {
+        let mut packet = Packet::new(PacketHeader {
+            ack: 0,
+            ack_bits: 0,
+        });
+
+        let message = Message::JoinReject {
+            id: 0,
+            reason: "-".repeat(400),
+        }
+        .encode();
+
+        assert!(packet.push(&message));
+        assert!(packet.push(&message));
+        assert!(!packet.push(&message), "No room left");
+        assert!(packet.as_bytes().len() <= PACKET_SIZE_MAX);
+    }
+
+    #[test]
+    fn test_message_size_max() {
+        let mut packet = Packet::new(PacketHeader {
+            ack: u16::MAX,
+            ack_bits: u32::MAX,
+        });
+
+        assert_eq!(PACKET_HEADER_SIZE_MAX, packet.as_bytes().len());
+        assert!(packet.push(&[0; MESSAGE_SIZE_MAX]));
+        assert!(!packet.push(&[0]));
+    }
+}
@@ -0,0 +1,91 @@
+use crate::utils::DurationExt;
+use amethyst::core::math::Vector2;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Positions of collidable entities at recent server ticks, oldest first. Lets hit-testing be
+/// rewound to the moment a client saw
+#[derive(Default)]
+pub struct PositionHistory {
+    ticks: VecDeque<(Duration, HashMap<u32, Vector2<f32>>)>,
+}
+
+impl PositionHistory {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Records positions at the time and forgets ticks which are no longer needed to rewind
+    /// within the window
+    pub fn push(
+        &mut self,
+        time: Duration,
+        positions: HashMap<u32, Vector2<f32>>,
+        window: Duration,
+    ) {
+        let oldest = time.sub_safely(window);
+
+        // Keep a tick at or before the window start to interpolate from
+        while self.ticks.get(1).map_or(false, |(t, _)| *t <= oldest) {
+            self.ticks.pop_front();
+        }
+
+        self.ticks.push_back((time, positions));
+    }
+
+    /// Position of the entity at the time, interpolated between the closest ticks. Returns nothing
+    /// if the time is past the latest tick or the entity wasn't recorded by then
+    pub fn get(&self, entity_id: u32, time: Duration) -> Option<Vector2<f32>> {
+        let mut previous: Option<(Duration, Vector2<f32>)> = None;
+
+        for (tick_time, positions) in &self.ticks {
+            let position = positions.get(&entity_id).copied();
+
+            if *tick_time >= time {
+                return match (previous, position) {
+                    (Some((previous_time, previous_position)), Some(position)) => {
+                        let progress = time.get_progress(previous_time, *tick_time);
+                        Some(previous_position + (position - previous_position) * progress)
+                    }
+                    (None, position) => position,
+                    (Some((_, previous_position)), None) => Some(previous_position),
+                };
+            }
+
+            previous = position.map(|p| (*tick_time, p));
+        }
+
+        return None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    fn positions(x: f32) -> HashMap<u32, Vector2<f32>> {
+        let mut positions = HashMap::new();
+        positions.insert(1, Vector2::new(x, 0.0));
+        return positions;
+    }
+
+    #[test]
+    fn test_get() {
+        let window = Duration::from_millis(100);
+        let mut history = PositionHistory::new();
+
+        for i in 0..10 {
+            history.push(Duration::from_millis(i * 20), positions(i as f32), window);
+        }
+
+        let x = |millis| history.get(1, Duration::from_millis(millis)).map(|p| p.x);
+
+        assert_abs_diff_eq!(4.5, x(90).unwrap_or_default());
+        assert_abs_diff_eq!(9.0, x(180).unwrap_or_default());
+        assert_eq!(Some(4.0), x(0), "Forgotten ones are clamped to the oldest");
+        assert_eq!(None, x(200), "Not recorded yet");
+        assert_eq!(None, history.get(2, Duration::from_millis(90)), "Unknown");
+    }
+}
@@ -1,4 +1,22 @@
+use crate::resources::InputEcho;
 use crate::utils::Position;
 use std::collections::HashMap;
 
-pub type PositionUpdateResource = HashMap<u32, Position>;
+/// Positions received from the server since the previous frame
+#[derive(Default)]
+pub struct PositionUpdateResource {
+    pub positions: HashMap<u32, Position>,
+    /// The latest player input applied by the server at the moment of the positions
+    pub input: Option<InputEcho>,
+}
+
+impl PositionUpdateResource {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    pub fn clear(&mut self) {
+        self.positions.clear();
+        self.input = None;
+    }
+}
@@ -0,0 +1,135 @@
+use crate::components::ActorActions;
+use crate::components::RigidBody;
+use crate::resources::InputEcho;
+use crate::utils::SequenceExt;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+/// Around 4 seconds at 60 FPS
+const HISTORY_SIZE: usize = 256;
+
+/// Player inputs sent to the server and frames predicted locally since then
+pub struct PredictionResource {
+    input_sequence: u16,
+    frames: VecDeque<PredictedFrame>,
+    /// Player body after the latest forgotten frame
+    confirmed_body: Option<RigidBody>,
+}
+
+pub struct PredictedFrame {
+    /// The latest input sent by the moment of the frame
+    pub input_sequence: u16,
+    pub actions: ActorActions,
+    pub direction: f32,
+    pub delta: f32,
+    /// Player body after the frame
+    pub body: RigidBody,
+}
+
+impl PredictionResource {
+    pub fn new() -> Self {
+        return Self {
+            input_sequence: 0,
+            frames: VecDeque::new(),
+            confirmed_body: None,
+        };
+    }
+
+    pub fn next_input_sequence(&mut self) -> u16 {
+        self.input_sequence = self.input_sequence.wrapping_add(1);
+        return self.input_sequence;
+    }
+
+    pub fn push(&mut self, actions: ActorActions, direction: f32, delta: f32, body: &RigidBody) {
+        if self.frames.len() >= HISTORY_SIZE {
+            if let Some(frame) = self.frames.pop_front() {
+                self.confirmed_body = Some(frame.body);
+            }
+        }
+
+        self.frames.push_back(PredictedFrame {
+            input_sequence: self.input_sequence,
+            actions,
+            direction,
+            delta,
+            body: body.clone(),
+        });
+    }
+
+    /// Forgets frames of inputs older than the echoed one. Returns player body by the moment of the
+    /// echo, unknown if nothing is forgotten yet, and frames the server hasn't simulated yet, which
+    /// are to be replayed
+    pub fn confirm(&mut self, echo: InputEcho) -> (Option<RigidBody>, Vec<&PredictedFrame>) {
+        while let Some(frame) = self.frames.front() {
+            if frame.input_sequence.cmp_sequence(echo.sequence) != Ordering::Less {
+                break;
+            }
+
+            if let Some(frame) = self.frames.pop_front() {
+                self.confirmed_body = Some(frame.body);
+            }
+        }
+
+        // The server may have simulated the echoed input for only a part of its frames yet
+        let age = f32::from(echo.age) / 1000.0;
+        let mut body = self.confirmed_body.as_ref();
+        let mut elapsed = 0.0;
+        let mut unconfirmed = Vec::with_capacity(self.frames.len());
+
+        for frame in &self.frames {
+            if frame.input_sequence == echo.sequence && elapsed + frame.delta <= age {
+                elapsed += frame.delta;
+                body = Some(&frame.body);
+            } else {
+                unconfirmed.push(frame);
+            }
+        }
+
+        return (body.cloned(), unconfirmed);
+    }
+}
+
+impl Default for PredictionResource {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst::core::math::Vector2;
+
+    fn push(prediction: &mut PredictionResource, velocity: f32) {
+        let mut body = RigidBody::new(1.0, 0.0, 0.0, 0.0);
+        body.velocity = Vector2::new(velocity, 0.0);
+        prediction.push(ActorActions::empty(), 0.0, 0.1, &body);
+    }
+
+    fn confirm(prediction: &mut PredictionResource, sequence: u16, age: u16) -> (f32, usize) {
+        let (body, frames) = prediction.confirm(InputEcho { sequence, age });
+        return (body.map_or(0.0, |b| b.velocity.x), frames.len());
+    }
+
+    #[test]
+    fn test_confirm() {
+        let mut prediction = PredictionResource::new();
+        push(&mut prediction, 1.0);
+        prediction.next_input_sequence();
+        push(&mut prediction, 2.0);
+        push(&mut prediction, 3.0);
+        push(&mut prediction, 4.0);
+        prediction.next_input_sequence();
+        push(&mut prediction, 5.0);
+
+        assert_eq!((1.0, 4), confirm(&mut prediction, 1, 0), "Just applied");
+        assert_eq!(
+            (3.0, 2),
+            confirm(&mut prediction, 1, 250),
+            "Applied for a while"
+        );
+        assert_eq!((1.0, 4), confirm(&mut prediction, 1, 0), "Not forgotten");
+        assert_eq!((4.0, 1), confirm(&mut prediction, 2, 0), "Next input");
+        assert_eq!((5.0, 0), confirm(&mut prediction, 2, 1000), "All simulated");
+    }
+}
@@ -0,0 +1,302 @@
+use crate::utils;
+use amethyst::ecs::Entity;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::time::Duration;
+
+pub const PLAYER_NAME_LENGTH_MAX: usize = 15;
+/// The most players a game may have, so the roster still fits a single packet
+pub const PLAYERS_MAX: usize = 16;
+const PLAYER_NAME_DEFAULT: &str = "Player";
+/// Smaller ping changes aren't worth sending the whole roster again
+const PING_CHANGE_MIN: u16 = 10;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RosterPlayer {
+    pub id: u16,
+    pub name: String,
+    pub kills: u16,
+    pub deaths: u16,
+    /// Round trip time in milliseconds
+    pub ping: u16,
+}
+
+/// Players of the game with their scores. The server owns it and replicates it to clients
+#[derive(Default)]
+pub struct RosterResource {
+    players: Vec<RosterPlayer>,
+    /// Which player controls the actor. Known by the server only
+    actors: HashMap<Entity, u16>,
+    next_id: u16,
+    is_changed: bool,
+}
+
+impl RosterResource {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Adds the player controlling the actor. Returns the name the player has got, which is
+    /// made unique among others
+    pub fn add(&mut self, name: &str, actor: Entity) -> String {
+        let name = self.to_unique_name(&sanitize_name(name));
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+
+        self.players.push(RosterPlayer {
+            id,
+            name: name.clone(),
+            kills: 0,
+            deaths: 0,
+            ping: 0,
+        });
+
+        self.actors.insert(actor, id);
+        self.is_changed = true;
+
+        return name;
+    }
+
+    pub fn remove(&mut self, actor: Entity) {
+        if let Some(id) = self.actors.remove(&actor) {
+            self.players.retain(|p| p.id != id);
+            self.is_changed = true;
+        }
+    }
+
+    /// Lets the player control another actor keeping the score
+    pub fn replace_actor(&mut self, old: Entity, new: Entity) {
+        if let Some(id) = self.actors.remove(&old) {
+            self.actors.insert(new, id);
+        }
+    }
+
+    pub fn reset_scores(&mut self) {
+        for player in &mut self.players {
+            player.kills = 0;
+            player.deaths = 0;
+        }
+
+        self.is_changed = true;
+    }
+
+    /// Credits a death to the victim and a kill to the killer if they are players
+    pub fn on_death(&mut self, victim: Entity, killer: Option<Entity>) {
+        if let Some(player) = self.get_player_mut(victim) {
+            player.deaths = player.deaths.saturating_add(1);
+            self.is_changed = true;
+        }
+
+        if let Some(killer) = killer.filter(|k| *k != victim) {
+            if let Some(player) = self.get_player_mut(killer) {
+                player.kills = player.kills.saturating_add(1);
+                self.is_changed = true;
+            }
+        }
+    }
+
+    pub fn set_ping(&mut self, actor: Entity, rtt: Duration) {
+        let ping = u16::try_from(rtt.as_millis()).unwrap_or(u16::MAX);
+
+        if let Some(player) = self.get_player_mut(actor) {
+            if player.ping.max(ping) - player.ping.min(ping) >= PING_CHANGE_MIN {
+                player.ping = ping;
+                self.is_changed = true;
+            }
+        }
+    }
+
+    /// Takes players as told by the server
+    pub fn replace(&mut self, players: Vec<RosterPlayer>) {
+        self.players = players;
+    }
+
+    /// Whether the server has changed the roster since the last call
+    pub fn take_changed(&mut self) -> bool {
+        let is_changed = self.is_changed;
+        self.is_changed = false;
+        return is_changed;
+    }
+
+    pub fn get_name(&self, actor: Entity) -> Option<&str> {
+        let id = *self.actors.get(&actor)?;
+
+        return self
+            .players
+            .iter()
+            .find(|p| p.id == id)
+            .map(|p| p.name.as_str());
+    }
+
+    pub fn get_actor(&self, id: u16) -> Option<Entity> {
+        return self
+            .actors
+            .iter()
+            .find(|(_, player_id)| **player_id == id)
+            .map(|(actor, _)| *actor);
+    }
+
+    pub fn get_players(&self) -> &[RosterPlayer] {
+        return &self.players;
+    }
+
+    fn get_player_mut(&mut self, actor: Entity) -> Option<&mut RosterPlayer> {
+        let id = *self.actors.get(&actor)?;
+        return self.players.iter_mut().find(|p| p.id == id);
+    }
+
+    fn to_unique_name(&self, name: &str) -> String {
+        let mut unique = name.to_string();
+        let mut suffix = 1;
+
+        while self.players.iter().any(|p| p.name == unique) {
+            suffix += 1;
+            let suffix = format!(" {}", suffix);
+            let length = PLAYER_NAME_LENGTH_MAX.saturating_sub(suffix.chars().count());
+            unique = name.chars().take(length).collect::<String>() + &suffix;
+        }
+
+        return unique;
+    }
+}
+
+fn sanitize_name(name: &str) -> String {
+    let name = utils::sanitize_text(name, PLAYER_NAME_LENGTH_MAX);
+
+    if name.is_empty() {
+        return PLAYER_NAME_DEFAULT.to_string();
+    } else {
+        return name;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::Message;
+    use crate::resources::MESSAGE_SIZE_MAX;
+    use amethyst::ecs::Builder;
+    use amethyst::ecs::World;
+    use amethyst::ecs::WorldExt;
+
+    #[test]
+    fn test_players_max_fit() {
+        let player = RosterPlayer {
+            id: u16::MAX,
+            name: "\u{1f9df}".repeat(PLAYER_NAME_LENGTH_MAX),
+            kills: u16::MAX,
+            deaths: u16::MAX,
+            ping: u16::MAX,
+        };
+
+        let message = Message::Roster {
+            id: u16::MAX,
+            players: vec![player; PLAYERS_MAX],
+        };
+
+        assert!(message.encode().len() <= MESSAGE_SIZE_MAX);
+    }
+
+    #[test]
+    fn test_add() {
+        let mut world = World::new();
+        let mut roster = RosterResource::new();
+
+        assert_eq!(
+            "Petrukha",
+            roster.add(" Petrukha\n", world.create_entity().build())
+        );
+        assert_eq!(
+            "Petrukha 2",
+            roster.add("Petrukha", world.create_entity().build())
+        );
+        assert_eq!("Player", roster.add("   ", world.create_entity().build()));
+        assert_eq!(
+            "Extremely long",
+            roster.add("Extremely long name", world.create_entity().build()),
+        );
+        assert_eq!(
+            "Extremely lon 2",
+            roster.add("Extremely long name", world.create_entity().build()),
+            "Shortened to fit the suffix",
+        );
+
+        assert!(roster.take_changed());
+        assert!(!roster.take_changed());
+    }
+
+    #[test]
+    fn test_on_death() {
+        let mut world = World::new();
+        let mut roster = RosterResource::new();
+        let killer = world.create_entity().build();
+        let victim = world.create_entity().build();
+        let zombie = world.create_entity().build();
+
+        roster.add("Killer", killer);
+        roster.add("Victim", victim);
+
+        roster.on_death(victim, Some(killer));
+        roster.on_death(zombie, Some(killer));
+        roster.on_death(killer, Some(killer));
+        roster.on_death(victim, Some(zombie));
+        roster.remove(zombie);
+
+        let scores = roster
+            .get_players()
+            .iter()
+            .map(|p| (p.name.as_str(), p.kills, p.deaths))
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec![("Killer", 2, 1), ("Victim", 0, 2)], scores);
+
+        roster.remove(victim);
+        assert_eq!(1, roster.get_players().len());
+    }
+
+    #[test]
+    fn test_replace_actor() {
+        let mut world = World::new();
+        let mut roster = RosterResource::new();
+        let old = world.create_entity().build();
+        let new = world.create_entity().build();
+
+        roster.add("Petrukha", old);
+        roster.on_death(old, None);
+        roster.replace_actor(old, new);
+
+        assert_eq!(None, roster.get_name(old));
+        assert_eq!(Some("Petrukha"), roster.get_name(new));
+        assert_eq!(Some(new), roster.get_actor(0));
+        assert_eq!(
+            Some(1),
+            roster.get_players().first().map(|p| p.deaths),
+            "Kept the score",
+        );
+
+        roster.reset_scores();
+        assert_eq!(Some(0), roster.get_players().first().map(|p| p.deaths));
+    }
+
+    #[test]
+    fn test_set_ping() {
+        let mut world = World::new();
+        let mut roster = RosterResource::new();
+        let actor = world.create_entity().build();
+
+        roster.add("Petrukha", actor);
+        roster.take_changed();
+
+        roster.set_ping(actor, Duration::from_millis(9));
+        assert!(!roster.take_changed(), "Jitter");
+
+        roster.set_ping(actor, Duration::from_millis(40));
+        assert!(roster.take_changed());
+
+        roster.set_ping(actor, Duration::from_millis(31));
+        assert!(!roster.take_changed(), "Jitter");
+        assert_eq!(Some(40), roster.get_players().first().map(|p| p.ping));
+    }
+}
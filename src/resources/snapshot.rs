@@ -0,0 +1,425 @@
+use crate::resources::InputEcho;
+use crate::resources::Message;
+use crate::resources::MESSAGE_SIZE_MAX;
+use crate::utils::Position;
+use crate::utils::SequenceExt;
+use serde::Deserialize;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::f32::consts::TAU;
+
+/// How many position units make one meter
+const POSITION_PRECISION: f32 = 100.0;
+/// How many direction units make a full turn
+const DIRECTION_PRECISION: f32 = 65536.0;
+const HISTORY_SIZE: usize = 32;
+
+/// The most bytes an entity ID with its position delta takes with varint encoding
+const ENTITY_SIZE_MAX: usize = 18;
+/// The most bytes a removed entity ID takes with varint encoding
+const REMOVED_SIZE_MAX: usize = 5;
+/// Leaves room for the other fields of a snapshot message
+const PART_SIZE_MAX: usize = MESSAGE_SIZE_MAX - 32;
+
+/// A position compressed for network. Differences between such positions are small numbers so
+/// they take few bytes with varint encoding
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct QuantizedPosition {
+    x: i32,
+    y: i32,
+    direction: i16,
+}
+
+/// Positions of entities at a tick
+#[derive(Clone, Default)]
+pub struct Snapshot {
+    positions: HashMap<u32, QuantizedPosition>,
+}
+
+/// Recent snapshots, oldest first
+#[derive(Default)]
+pub struct SnapshotHistory {
+    snapshots: VecDeque<(u16, Snapshot)>,
+    /// The latest snapshot which hasn't got all its parts yet
+    incomplete: Option<IncompleteSnapshot>,
+}
+
+struct IncompleteSnapshot {
+    tick: u16,
+    parts: u8,
+    received: HashSet<u8>,
+    snapshot: Snapshot,
+}
+
+impl QuantizedPosition {
+    #[must_use]
+    pub const fn delta(self, base: Self) -> Self {
+        return Self {
+            x: self.x.wrapping_sub(base.x),
+            y: self.y.wrapping_sub(base.y),
+            direction: self.direction.wrapping_sub(base.direction),
+        };
+    }
+
+    #[must_use]
+    pub const fn apply_delta(self, delta: Self) -> Self {
+        return Self {
+            x: self.x.wrapping_add(delta.x),
+            y: self.y.wrapping_add(delta.y),
+            direction: self.direction.wrapping_add(delta.direction),
+        };
+    }
+}
+
+impl From<Position> for QuantizedPosition {
+    #[allow(clippy::cast_possible_truncation)]
+    fn from(position: Position) -> Self {
+        return Self {
+            x: (position.x * POSITION_PRECISION).round() as i32,
+            y: (position.y * POSITION_PRECISION).round() as i32,
+            // Casting through `i64` wraps the direction within a single turn
+            direction: ((position.direction / TAU * DIRECTION_PRECISION).round() as i64) as i16,
+        };
+    }
+}
+
+impl From<QuantizedPosition> for Position {
+    fn from(position: QuantizedPosition) -> Self {
+        return Self::new(
+            position.x as f32 / POSITION_PRECISION,
+            position.y as f32 / POSITION_PRECISION,
+            f32::from(position.direction) / DIRECTION_PRECISION * TAU,
+        );
+    }
+}
+
+impl Snapshot {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    pub fn insert(&mut self, entity_id: u32, position: Position) {
+        self.positions.insert(entity_id, position.into());
+    }
+
+    /// Creates messages of positions changed since the base snapshot or all of them if there's no
+    /// base. Usually it's a single message, but there're more parts if it doesn't fit a packet.
+    /// The position of the receiver's own entity is always included and every part has the input
+    /// echo, so the receiver can reconcile its prediction
+    pub fn to_messages(
+        &self,
+        tick: u16,
+        base: Option<(u16, &Self)>,
+        own: Option<(u32, InputEcho)>,
+    ) -> Vec<Message> {
+        let mut parts = vec![(Vec::new(), Vec::new())];
+        let mut part_size = 0;
+
+        for (entity_id, position) in &self.positions {
+            let base_position = base.and_then(|(_, b)| b.positions.get(entity_id));
+            let is_own = own.map_or(false, |(id, _)| id == *entity_id);
+
+            if base_position != Some(position) || is_own {
+                if part_size + ENTITY_SIZE_MAX > PART_SIZE_MAX {
+                    parts.push((Vec::new(), Vec::new()));
+                    part_size = 0;
+                }
+
+                let delta = position.delta(base_position.copied().unwrap_or_default());
+                part_size += ENTITY_SIZE_MAX;
+
+                if let Some((entities, _)) = parts.last_mut() {
+                    entities.push((*entity_id, delta));
+                }
+            }
+        }
+
+        if let Some((_, base)) = base {
+            for entity_id in base.positions.keys() {
+                if !self.positions.contains_key(entity_id) {
+                    if part_size + REMOVED_SIZE_MAX > PART_SIZE_MAX {
+                        parts.push((Vec::new(), Vec::new()));
+                        part_size = 0;
+                    }
+
+                    part_size += REMOVED_SIZE_MAX;
+
+                    if let Some((_, removed)) = parts.last_mut() {
+                        removed.push(*entity_id);
+                    }
+                }
+            }
+        }
+
+        // Hardly reachable, but parts beyond the count limit are left out
+        let count = u8::try_from(parts.len()).unwrap_or(u8::MAX);
+
+        return parts
+            .into_iter()
+            .zip(0..count)
+            .map(|((entities, removed), part)| Message::Snapshot {
+                tick,
+                base_tick: base.map(|(base_tick, _)| base_tick),
+                part,
+                parts: count,
+                entities,
+                removed,
+                input: own.map(|(_, input)| input),
+            })
+            .collect();
+    }
+}
+
+impl SnapshotHistory {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    pub fn push(&mut self, tick: u16, snapshot: Snapshot) {
+        if self.snapshots.len() >= HISTORY_SIZE {
+            self.snapshots.pop_front();
+        }
+
+        self.snapshots.push_back((tick, snapshot));
+    }
+
+    pub fn get(&self, tick: u16) -> Option<&Snapshot> {
+        return self
+            .snapshots
+            .iter()
+            .rev()
+            .find(|(t, _)| *t == tick)
+            .map(|(_, s)| s);
+    }
+
+    pub fn get_latest_tick(&self) -> Option<u16> {
+        return self.snapshots.back().map(|(tick, _)| *tick);
+    }
+
+    /// Applies a part of a received snapshot and returns positions of changed entities. The
+    /// snapshot gets to the history once all its parts are applied. Returns nothing if the
+    /// snapshot isn't newer than the latest one, its base is unknown or the part is a duplicate
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply(
+        &mut self,
+        tick: u16,
+        base_tick: Option<u16>,
+        part: u8,
+        parts: u8,
+        entities: &[(u32, QuantizedPosition)],
+        removed: &[u32],
+    ) -> Option<Vec<(u32, Position)>> {
+        if let Some(latest_tick) = self.get_latest_tick() {
+            if tick.cmp_sequence(latest_tick) != Ordering::Greater {
+                return None;
+            }
+        }
+
+        if part >= parts {
+            return None;
+        }
+
+        match self.incomplete.as_ref().map(|s| tick.cmp_sequence(s.tick)) {
+            Some(Ordering::Less) => {
+                return None;
+            }
+            Some(Ordering::Equal) => {}
+            Some(Ordering::Greater) | None => {
+                // A newer snapshot replaces the incomplete one, which won't be needed anymore
+                let snapshot;
+
+                if let Some(base_tick) = base_tick {
+                    snapshot = self.get(base_tick)?.clone();
+                } else {
+                    snapshot = Snapshot::new();
+                }
+
+                self.incomplete = Some(IncompleteSnapshot {
+                    tick,
+                    parts,
+                    received: HashSet::new(),
+                    snapshot,
+                });
+            }
+        }
+
+        let incomplete = self.incomplete.as_mut()?;
+
+        if !incomplete.received.insert(part) {
+            return None;
+        }
+
+        let mut changed = Vec::with_capacity(entities.len());
+
+        for (entity_id, delta) in entities {
+            let position = incomplete.snapshot.positions.entry(*entity_id).or_default();
+            *position = position.apply_delta(*delta);
+            changed.push((*entity_id, Position::from(*position)));
+        }
+
+        for entity_id in removed {
+            incomplete.snapshot.positions.remove(entity_id);
+        }
+
+        if incomplete.received.len() >= usize::from(incomplete.parts) {
+            if let Some(complete) = self.incomplete.take() {
+                self.push(complete.tick, complete.snapshot);
+            }
+        }
+
+        return Some(changed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use std::f32::consts::PI;
+
+    fn transfer(messages: &[Message], history: &mut SnapshotHistory) -> Option<Vec<u32>> {
+        let mut changed = None;
+
+        for message in messages {
+            if let Message::Snapshot {
+                tick,
+                base_tick,
+                part,
+                parts,
+                ref entities,
+                ref removed,
+                ..
+            } = *message
+            {
+                if let Some(part_changed) =
+                    history.apply(tick, base_tick, part, parts, entities, removed)
+                {
+                    changed
+                        .get_or_insert_with(Vec::new)
+                        .extend(part_changed.iter().map(|(id, _)| *id));
+                }
+            }
+        }
+
+        return changed;
+    }
+
+    #[test]
+    fn test_quantization() {
+        let position = Position::new(12.345, -0.5, 3.0);
+        let restored = Position::from(QuantizedPosition::from(position));
+        assert_abs_diff_eq!(position.x, restored.x, epsilon = 0.01);
+        assert_abs_diff_eq!(position.y, restored.y, epsilon = 0.01);
+        assert_abs_diff_eq!(position.direction, restored.direction, epsilon = 0.001);
+
+        let a = QuantizedPosition::from(Position::new(0.0, 0.0, PI - 0.01));
+        let b = QuantizedPosition::from(Position::new(0.0, 0.0, 0.01 - PI));
+        let delta = b.delta(a);
+        assert!(
+            delta.direction.abs() < 300,
+            "Shortest way across the half turn"
+        );
+        assert_eq!(b, a.apply_delta(delta));
+    }
+
+    #[test]
+    fn test_delta() {
+        let mut sent = SnapshotHistory::new();
+        let mut received = SnapshotHistory::new();
+
+        let mut snapshot = Snapshot::new();
+        snapshot.insert(1, Position::new(1.0, 2.0, 0.5));
+        snapshot.insert(2, Position::new(3.0, 4.0, 0.5));
+        let full = snapshot.to_messages(10, None, None);
+        sent.push(10, snapshot);
+
+        let mut changed = transfer(&full, &mut received).unwrap_or_default();
+        changed.sort_unstable();
+        assert_eq!(vec![1, 2], changed, "Full snapshot");
+
+        let mut snapshot = Snapshot::new();
+        snapshot.insert(1, Position::new(1.0, 2.5, 0.5));
+        snapshot.insert(3, Position::new(5.0, 6.0, 0.5));
+        let delta = snapshot.to_messages(11, sent.get(10).map(|s| (10, s)), None);
+
+        assert!(matches!(
+            delta.as_slice(),
+            [Message::Snapshot { ref removed, .. }] if removed == &[2],
+        ));
+
+        let mut changed = transfer(&delta, &mut received).unwrap_or_default();
+        changed.sort_unstable();
+        assert_eq!(vec![1, 3], changed, "Only changed ones");
+        assert_eq!(Some(11), received.get_latest_tick());
+        assert!(received.get(11).map_or(false, |s| s.positions.len() == 2));
+
+        assert_eq!(None, transfer(&delta, &mut received), "Duplicate");
+        assert_eq!(None, transfer(&full, &mut received), "Stale");
+        assert_eq!(
+            None,
+            transfer(
+                &snapshot.to_messages(12, Some((5, &Snapshot::new())), None),
+                &mut received
+            ),
+            "Unknown base",
+        );
+
+        let echo = InputEcho {
+            sequence: 7,
+            age: 20,
+        };
+
+        let own = snapshot.to_messages(13, Some((11, &snapshot)), Some((3, echo)));
+        let mut changed = transfer(&own, &mut received).unwrap_or_default();
+        changed.sort_unstable();
+        assert_eq!(vec![3], changed, "Own one even if unchanged");
+        assert!(matches!(
+            own.as_slice(),
+            [Message::Snapshot { input: Some(e), .. }] if *e == echo,
+        ));
+    }
+
+    #[test]
+    fn test_split() {
+        let mut sent = SnapshotHistory::new();
+        let mut received = SnapshotHistory::new();
+        let mut snapshot = Snapshot::new();
+
+        for i in 0..250 {
+            let offset = i as f32 * 1000.0;
+            snapshot.insert(i, Position::new(offset, -offset, 3.0));
+        }
+
+        let full = snapshot.to_messages(10, None, None);
+        sent.push(10, snapshot);
+
+        assert!(full.len() > 1, "Split");
+        assert!(full.iter().all(|m| m.encode().len() <= MESSAGE_SIZE_MAX));
+
+        let (first, last) = full.split_at(full.len() - 1);
+
+        assert!(transfer(first, &mut received).is_some());
+        assert_eq!(None, received.get_latest_tick(), "Incomplete");
+        assert_eq!(None, transfer(first, &mut received), "Duplicates");
+        assert!(transfer(last, &mut received).is_some());
+        assert_eq!(Some(10), received.get_latest_tick(), "Complete");
+        assert!(received.get(10).map_or(false, |s| s.positions.len() == 250));
+
+        let removal = Snapshot::new().to_messages(11, sent.get(10).map(|s| (10, s)), None);
+        let changed = transfer(removal.get(1..).unwrap_or_default(), &mut received);
+        assert_eq!(Some(0), changed.map(|c| c.len()), "Nothing changed");
+        assert_eq!(Some(10), received.get_latest_tick(), "One part is missing");
+
+        let mut snapshot = Snapshot::new();
+        snapshot.insert(0, Position::new(1.0, 1.0, 0.0));
+        let next = snapshot.to_messages(12, sent.get(10).map(|s| (10, s)), None);
+        assert!(transfer(&next, &mut received).is_some(), "Newer replaces");
+        assert_eq!(Some(12), received.get_latest_tick());
+        assert!(received.get(12).map_or(false, |s| s.positions.len() == 1));
+        assert_eq!(None, transfer(&removal, &mut received), "Stale");
+    }
+}
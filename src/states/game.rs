@@ -1,47 +1,128 @@
 use crate::components::Actor;
 use crate::components::ActorActions;
 use crate::components::ActorType;
+use crate::components::ActorTypeSerialized;
 use crate::components::Health;
+use crate::components::Player;
 use crate::components::Projectile;
 use crate::components::RigidBody;
+use crate::components::Spectator;
+use crate::data::BUILD_HASH;
+use crate::data::VIEW_DISTANCE;
+use crate::models::ConsoleCommand;
 use crate::models::GameType;
+use crate::resources::sanitize_chat_text;
+use crate::resources::ChatResource;
 use crate::resources::EntityConverter;
 use crate::resources::GameTask;
 use crate::resources::GameTaskResource;
 use crate::resources::Message;
 use crate::resources::MouseInput;
 use crate::resources::NetResource;
+use crate::resources::RosterPlayer;
+use crate::resources::RosterResource;
+use crate::resources::Wallpaper;
+use crate::resources::PLAYERS_MAX;
+use crate::resources::PROTOCOL_VERSION;
 use crate::states::ui::HomeState;
+use crate::states::ui::NoticeState;
+use crate::systems::net::View;
 use crate::utils;
 use crate::utils::Position;
 use crate::utils::TakeContent;
 use crate::utils::WorldExtCustom;
 use amethyst::controls::HideCursor;
 use amethyst::core::transform::Transform;
+use amethyst::core::Parent;
 use amethyst::core::Time;
 use amethyst::ecs::Entity;
 use amethyst::ecs::Join;
 use amethyst::ecs::World;
+use amethyst::input::get_key;
 use amethyst::input::is_key_down;
 use amethyst::prelude::*;
+use amethyst::ui::UiFinder;
 use amethyst::winit::DeviceEvent;
 use amethyst::winit::ElementState;
 use amethyst::winit::Event;
 use amethyst::winit::MouseButton;
 use amethyst::winit::VirtualKeyCode;
 use amethyst::winit::WindowEvent;
+use rand::Rng;
 use std::net::SocketAddr;
+use std::time::Duration;
+
+const DISCONNECTED_TITLE: &str = "Disconnected";
+const CHAT_ID: &str = "chat";
+const CHAT_KEY: VirtualKeyCode = VirtualKeyCode::Return;
+const SPECTATOR_CYCLE_KEY: VirtualKeyCode = VirtualKeyCode::Space;
+const SPECTATOR_PLAY_KEY: VirtualKeyCode = VirtualKeyCode::P;
+const SERVER_SENDER: &str = "Server";
+const CONSOLE_SENDER: &str = "Console";
+/// Chat lines starting with it are run as console commands on the host
+const CONSOLE_PREFIX: char = '/';
+const KICK_REASON: &str = "By the admin";
+const BAN_REASON: &str = "Banned by the admin";
+const SHUTDOWN_REASON: &str = "Shut down by the admin";
+const SERVER_FULL_REASON: &str = "The server is full";
+/// Zombies spawned by the console appear this far from the center at most
+const ZOMBIE_SPAWN_RANGE: f32 = 20.0;
+const NET_STATS_ID: &str = "net_stats";
+const SCOREBOARD_ID: &str = "scoreboard";
+const SCOREBOARD_TEXT_ID: &str = "scoreboard.text";
+const SCOREBOARD_KEY: VirtualKeyCode = VirtualKeyCode::LAlt;
 
 pub struct GameState {
     game_type: GameType,
+    is_headless: bool,
     root: Option<Entity>,
+    /// The host's own actor
+    player: Option<Entity>,
+    /// Carries the camera until the client gets an actor
+    spectator: Option<Entity>,
+    chat: Option<Entity>,
+    net_stats: Option<Entity>,
+    is_net_stats_visible: bool,
+    scoreboard: Option<Entity>,
+    /// The players shown on the scoreboard while it's visible
+    scoreboard_players: Option<Vec<RosterPlayer>>,
+    disconnect_reason: Option<String>,
+    is_shutdown: bool,
 }
 
 impl GameState {
     pub const fn new(game_type: GameType) -> Self {
         return Self {
             game_type,
+            is_headless: false,
+            root: None,
+            player: None,
+            spectator: None,
+            chat: None,
+            net_stats: None,
+            is_net_stats_visible: false,
+            scoreboard: None,
+            scoreboard_players: None,
+            disconnect_reason: None,
+            is_shutdown: false,
+        };
+    }
+
+    /// Creates a state which runs without window and renderer and thus has no own player
+    pub const fn new_headless(game_type: GameType) -> Self {
+        return Self {
+            game_type,
+            is_headless: true,
             root: None,
+            player: None,
+            spectator: None,
+            chat: None,
+            net_stats: None,
+            is_net_stats_visible: false,
+            scoreboard: None,
+            scoreboard_players: None,
+            disconnect_reason: None,
+            is_shutdown: false,
         };
     }
 
@@ -49,7 +130,7 @@ impl GameState {
         let root = world.create_entity().build();
         self.root.replace(root);
 
-        if self.game_type.is_server() {
+        if self.game_type.is_server() && !self.is_headless {
             let entity = world.entities().create();
 
             world.create_actor(
@@ -63,24 +144,164 @@ impl GameState {
 
             world.set_actor_player(root, entity, &self.game_type);
 
-            for i in 0..2 {
-                let entity = world.entities().create();
+            let name = world
+                .read_resource::<NetResource>()
+                .get_config()
+                .player_name
+                .clone();
 
-                world.create_actor(
-                    root,
-                    entity,
-                    ActorType::ZOMBIE,
-                    Position::new(5.0 * (0.5 - i as f32), 0.0, 0.0),
-                    false,
-                    &self.game_type,
-                );
+            world.write_resource::<RosterResource>().add(&name, entity);
+            self.player = Some(entity);
+        }
+
+        if self.game_type.is_server() {
+            self.spawn_initial_zombies(world);
+        }
 
-                world.set_actor_ai(entity);
+        if !self.game_type.is_server() && !self.is_headless {
+            // A replay may have no actor granted, so watch it freely until then
+            let is_spectator = world
+                .try_fetch::<NetResource>()
+                .map_or(false, |n| n.get_config().spectate || n.is_replay());
+
+            if is_spectator {
+                self.spectator = Some(world.create_spectator(root));
             }
         }
 
-        world.create_terrain(root);
-        utils::world_decorations::create_decorations(world, root);
+        if !self.is_headless {
+            world.create_terrain(root);
+            utils::world_decorations::create_decorations(world, root);
+        }
+    }
+
+    fn spawn_initial_zombies(&self, world: &mut World) {
+        for i in 0..2 {
+            self.spawn_zombie(world, Position::new(5.0 * (0.5 - i as f32), 0.0, 0.0));
+        }
+    }
+
+    fn spawn_zombie(&self, world: &mut World, position: Position) {
+        if let Some(root) = self.root {
+            let entity = world.entities().create();
+
+            world.create_actor(
+                root,
+                entity,
+                ActorType::ZOMBIE,
+                position,
+                false,
+                &self.game_type,
+            );
+
+            world.set_actor_ai(entity);
+        }
+    }
+
+    fn set_net_stats_visibility(&self, world: &World, is_visibility: bool) {
+        if let Some(net_stats) = self.net_stats {
+            utils::set_entity_visibility(world, net_stats, is_visibility);
+        }
+    }
+
+    fn set_chat_visibility(&self, world: &World, is_visibility: bool) {
+        if let Some(chat) = self.chat {
+            if !is_visibility {
+                world.write_resource::<ChatResource>().close_input();
+            }
+
+            utils::set_entity_visibility(world, chat, is_visibility);
+        }
+    }
+
+    /// Types into the open chat input, sending the text on enter
+    fn on_chat_event(&self, world: &World, event: &Event) {
+        let mut text = None;
+        let mut is_closed = false;
+
+        {
+            let mut chat = world.write_resource::<ChatResource>();
+
+            if let Event::WindowEvent {
+                event: WindowEvent::ReceivedCharacter(c),
+                ..
+            } = *event
+            {
+                chat.type_char(c);
+            }
+
+            if is_key_down(event, VirtualKeyCode::Back) {
+                chat.erase_char();
+            }
+
+            if is_key_down(event, CHAT_KEY) {
+                text = chat.close_input();
+                is_closed = true;
+            } else if is_key_down(event, VirtualKeyCode::Escape) {
+                chat.close_input();
+                is_closed = true;
+            }
+        }
+
+        if is_closed {
+            utils::ui::set_cursor_visibility(world, false);
+        }
+
+        if let Some(text) = text {
+            let line = text
+                .strip_prefix(CONSOLE_PREFIX)
+                .filter(|_| self.game_type.is_server());
+
+            if let Some(line) = line {
+                match ConsoleCommand::parse(line) {
+                    Ok(command) => {
+                        world
+                            .write_resource::<GameTaskResource>()
+                            .push(GameTask::ConsoleCommand(command));
+                    }
+                    Err(error) => {
+                        self.print_console(world, &error);
+                    }
+                }
+            } else if self.game_type.is_server() {
+                if let Some(player) = self.player {
+                    self.on_task_chat_say(world, player, &text);
+                }
+            } else {
+                world
+                    .write_resource::<NetResource>()
+                    .send_to_all(Message::ChatSay { id: 0, text });
+            }
+        }
+    }
+
+    fn set_scoreboard_visibility(&mut self, world: &World, is_visibility: bool) {
+        if let Some(scoreboard) = self.scoreboard {
+            utils::set_entity_visibility(world, scoreboard, is_visibility);
+        }
+
+        if is_visibility {
+            let players = world
+                .read_resource::<RosterResource>()
+                .get_players()
+                .to_vec();
+
+            utils::ui::set_text(world, SCOREBOARD_TEXT_ID, format_scoreboard(&players));
+            self.scoreboard_players = Some(players);
+        } else {
+            self.scoreboard_players = None;
+        }
+    }
+
+    fn update_scoreboard(&mut self, world: &World) {
+        if let Some(shown) = self.scoreboard_players.as_mut() {
+            let roster = world.read_resource::<RosterResource>();
+
+            if shown.as_slice() != roster.get_players() {
+                *shown = roster.get_players().to_vec();
+                utils::ui::set_text(world, SCOREBOARD_TEXT_ID, format_scoreboard(shown));
+            }
+        }
     }
 
     fn on_task(&mut self, world: &mut World, task: &GameTask) {
@@ -88,28 +309,53 @@ impl GameState {
             GameTask::Start => {
                 // Skip since this should be processed while `ConnectingSate`
             }
-            GameTask::ClientJoin(address) => {
-                self.on_task_client_join(world, address);
+            GameTask::ClientJoin {
+                address,
+                protocol_version,
+                ref build,
+            } => {
+                self.on_task_client_join(world, address, protocol_version, build);
+            }
+            GameTask::ClientJoinDetails {
+                address,
+                ref name,
+                is_spectator,
+                session_token,
+            } => {
+                self.on_task_client_join_details(world, address, name, is_spectator, session_token);
+            }
+            GameTask::PlayRequest(address) => {
+                self.on_task_play_request(world, address);
             }
             GameTask::ActorSpawn {
                 entity,
                 actor_type,
                 position,
+                health,
             } => {
                 self.on_task_actor_spawn(world, entity, actor_type, position);
+                self.on_task_actor_health(world, entity, health);
             }
             GameTask::ActorGrant { entity } => {
                 self.on_task_actor_grant(world, entity);
             }
+            GameTask::ActorHealth { entity, health } => {
+                self.on_task_actor_health(world, entity, health);
+            }
             GameTask::ActorAction {
                 entity,
                 actions,
                 direction,
+                input_sequence,
             } => {
-                self.on_task_actor_action(world, entity, Some(actions), direction);
+                self.on_task_actor_action(world, entity, Some(actions), direction, input_sequence);
             }
-            GameTask::ActorTurn { entity, direction } => {
-                self.on_task_actor_action(world, entity, None, direction);
+            GameTask::ActorTurn {
+                entity,
+                direction,
+                input_sequence,
+            } => {
+                self.on_task_actor_action(world, entity, None, direction, input_sequence);
             }
             GameTask::ProjectileSpawn {
                 position,
@@ -129,44 +375,188 @@ impl GameState {
                 entity,
                 force_x,
                 force_y,
+                shooter,
             } => {
-                self.on_task_projectile_hit(world, entity, force_x, force_y);
+                self.on_task_projectile_hit(world, entity, force_x, force_y, shooter);
             }
             GameTask::EntityDelete(entity) => {
                 self.on_task_entity_delete(world, entity);
             }
+            GameTask::Roster(ref players) => {
+                world
+                    .write_resource::<RosterResource>()
+                    .replace(players.clone());
+            }
+            GameTask::ChatSay { sender, ref text } => {
+                self.on_task_chat_say(world, sender, text);
+            }
+            GameTask::Chat {
+                ref sender,
+                ref text,
+            } => {
+                self.on_task_chat(world, sender, text);
+            }
+            GameTask::ConsoleCommand(ref command) => {
+                if self.game_type.is_server() {
+                    self.on_task_console_command(world, command);
+                }
+            }
+            GameTask::Disconnected(ref reason) => {
+                self.disconnect_reason = Some(reason.clone());
+            }
         }
     }
 
     #[allow(clippy::unused_self)]
-    fn on_task_client_join(&self, world: &mut World, address: SocketAddr) {
-        {
+    fn on_task_client_join(
+        &self,
+        world: &World,
+        address: SocketAddr,
+        protocol_version: u16,
+        build: &str,
+    ) {
+        let mut net = world.write_resource::<NetResource>();
+
+        if is_join_skipped(&net, &address) {
+            return;
+        }
+
+        let is_banned = net.is_banned(&address);
+
+        if let Err(reason) = check_join(protocol_version, build, is_banned) {
+            log::warn!("{} has been rejected: {}", address, reason);
+            net.reject(&address, &reason);
+        } else {
+            net.send_to(&address, Message::JoinProceed { id: 0 });
+        }
+    }
+
+    fn on_task_client_join_details(
+        &self,
+        world: &mut World,
+        address: SocketAddr,
+        name: &str,
+        is_spectator: bool,
+        session_token: Option<u64>,
+    ) {
+        if is_join_skipped(&world.read_resource::<NetResource>(), &address) {
+            return;
+        }
+
+        if let Some(session_token) = session_token {
             let mut net = world.write_resource::<NetResource>();
 
-            net.send_to(&address, Message::JoinAccept { id: 0 });
+            // The entity is kept as is, so the player gets position, health and score back
+            if let Some(entity) = net.resume_session(&address, session_token) {
+                net.send_to(
+                    &address,
+                    Message::JoinAccept {
+                        id: 0,
+                        session_token,
+                    },
+                );
+
+                // The roster is sent on changes only, so the rejoined client needs it now
+                let roster = world.read_resource::<RosterResource>();
 
-            for (entity, actor, transform) in (
-                &world.entities(),
-                &world.read_storage::<Actor>(),
-                &world.read_storage::<Transform>(),
-            )
-                .join()
-            {
                 net.send_to(
                     &address,
-                    Message::ActorSpawn {
+                    Message::Roster {
                         id: 0,
-                        entity_id: entity.id(),
-                        actor_type: actor.actor_type.serialized,
-                        position: transform.into(),
+                        players: roster.get_players().to_vec(),
                     },
                 );
+
+                let name = roster.get_name(entity).unwrap_or_default();
+                log::info!("{} has rejoined as {}", address, name);
+                return;
             }
         }
 
+        let players_max = world
+            .read_resource::<NetResource>()
+            .get_config()
+            .players_max
+            .min(PLAYERS_MAX);
+
+        // Rejoining players are still in the roster, so they aren't counted twice
+        if world.read_resource::<RosterResource>().get_players().len() >= players_max {
+            log::warn!("{} has been rejected: {}", address, SERVER_FULL_REASON);
+
+            world
+                .write_resource::<NetResource>()
+                .reject(&address, SERVER_FULL_REASON);
+
+            return;
+        }
+
+        let session_token = world
+            .write_resource::<NetResource>()
+            .start_session(&address);
+
+        world.write_resource::<NetResource>().send_to(
+            &address,
+            Message::JoinAccept {
+                id: 0,
+                session_token,
+            },
+        );
+
         if let Some(root) = self.root {
             let entity = world.entities().create();
 
+            if is_spectator {
+                // Identifies the spectator until it gets an actor
+                world.add(entity, Parent { entity: root });
+            } else {
+                world.create_actor(
+                    root,
+                    entity,
+                    ActorType::HUMAN,
+                    Position::default(),
+                    false,
+                    &self.game_type,
+                );
+            }
+
+            // The actor gets spawned and granted to the client once it becomes relevant
+            let mut net = world.write_resource::<NetResource>();
+            net.attach_entity(&address, entity);
+            net.set_spectator(&address, is_spectator);
+
+            let name = world.write_resource::<RosterResource>().add(name, entity);
+
+            if is_spectator {
+                log::info!("{} has joined as {} to spectate", address, name);
+            } else {
+                log::info!("{} has joined as {}", address, name);
+            }
+        }
+    }
+
+    fn on_task_play_request(&self, world: &World, address: SocketAddr) {
+        let mut net = world.write_resource::<NetResource>();
+
+        if !net.get_config().allow_spectator_play {
+            net.send_to(
+                &address,
+                Message::Chat {
+                    id: 0,
+                    sender: SERVER_SENDER.to_string(),
+                    text: "Spectators can't play on this server".to_string(),
+                },
+            );
+
+            return;
+        }
+
+        let entity = net
+            .connections
+            .get(&address)
+            .and_then(|c| c.attached_entity);
+
+        if let (Some(root), Some(entity)) = (self.root, entity) {
+            // The identifying entity becomes the actor, so the player keeps the score
             world.create_actor(
                 root,
                 entity,
@@ -176,17 +566,22 @@ impl GameState {
                 &self.game_type,
             );
 
-            let mut net = world.write_resource::<NetResource>();
+            net.set_spectator(&address, false);
+            log::info!("{} has switched into play", address);
+        }
+    }
 
-            net.send_to(
-                &address,
-                Message::ActorGrant {
-                    id: 0,
-                    entity_id: entity.id(),
-                },
-            );
+    fn cycle_spectator_target(world: &World, spectator: Entity) {
+        let mut candidates = (&world.entities(), &world.read_storage::<Actor>())
+            .join()
+            .filter(|(_, a)| matches!(a.actor_type.serialized, ActorTypeSerialized::Human))
+            .map(|(e, _)| e)
+            .collect::<Vec<Entity>>();
 
-            net.attach_entity(&address, entity);
+        candidates.sort();
+
+        if let Some(spectator) = world.write_storage::<Spectator>().get_mut(spectator) {
+            spectator.cycle_target(&candidates);
         }
     }
 
@@ -203,11 +598,31 @@ impl GameState {
     }
 
     fn on_task_actor_grant(&mut self, world: &mut World, entity: Entity) {
-        if let Some(root) = self.root {
+        if let Some(spectator) = self.spectator.take() {
+            if let Err(error) = world.delete_entity(spectator) {
+                log::error!("Failed to delete the spectator: {}", error);
+            }
+        }
+
+        let is_replay = world
+            .try_fetch::<NetResource>()
+            .map_or(false, |n| n.is_replay());
+
+        if is_replay {
+            // Nobody controls a replayed actor, so just watch it
+            world.create_camera(entity);
+        } else if let Some(root) = self.root {
             world.set_actor_player(root, entity, &self.game_type);
         }
     }
 
+    #[allow(clippy::unused_self)]
+    fn on_task_actor_health(&self, world: &World, entity: Entity, health: f32) {
+        if let Some(current) = world.write_storage::<Health>().get_mut(entity) {
+            current.set(health, world.read_resource::<Time>().absolute_time());
+        }
+    }
+
     #[allow(clippy::unused_self)]
     fn on_task_actor_action(
         &self,
@@ -215,6 +630,7 @@ impl GameState {
         entity: Entity,
         actions: Option<ActorActions>,
         direction: f32,
+        input_sequence: u16,
     ) {
         if let Some(actions) = actions {
             if let Some(actor) = world.write_storage::<Actor>().get_mut(entity) {
@@ -225,6 +641,10 @@ impl GameState {
         if let Some(transform) = world.write_storage::<Transform>().get_mut(entity) {
             transform.set_rotation_2d(direction);
         }
+
+        world
+            .write_resource::<NetResource>()
+            .acknowledge_input(entity, input_sequence);
     }
 
     fn on_task_projectile_spawn(
@@ -236,29 +656,58 @@ impl GameState {
         shooter: Option<Entity>,
     ) {
         if let Some(root) = self.root {
+            let mut rewind = Duration::from_millis(0);
+
             if self.game_type.is_server() {
-                world
-                    .write_resource::<NetResource>()
-                    .send_to_all(Message::ProjectileSpawn {
-                        id: 0,
-                        position,
-                        velocity,
-                        acceleration_factor,
-                        shooter_id: shooter.map(Entity::id),
-                    });
+                let mut net = world.write_resource::<NetResource>();
+                let transforms = world.read_storage::<Transform>();
+
+                // Clients which may see any part of the flight, even if the shooter is away
+                let distance =
+                    VIEW_DISTANCE + Projectile::calc_range(velocity, acceleration_factor);
+
+                for connection in net.connections.values_mut() {
+                    if View::of(connection, &transforms)
+                        .map_or(false, |v| v.contains(position.x, position.y, distance))
+                    {
+                        // Don't make the client track a shooter it doesn't know
+                        let shooter_id = shooter
+                            .filter(|s| connection.is_relevant(*s))
+                            .map(Entity::id);
+
+                        connection.send(&mut Message::ProjectileSpawn {
+                            id: 0,
+                            position,
+                            velocity,
+                            acceleration_factor,
+                            shooter_id,
+                        });
+                    }
+                }
+
+                if let Some(shooter) = shooter {
+                    rewind = net.get_rewind(shooter);
+                }
             }
 
-            world.create_projectile(root, position, velocity, acceleration_factor, shooter);
+            world.create_projectile(
+                root,
+                position,
+                velocity,
+                acceleration_factor,
+                shooter,
+                rewind,
+            );
         }
     }
 
-    #[allow(clippy::unused_self)]
     fn on_task_projectile_hit(
         &self,
         world: &mut World,
         entity: Entity,
         force_x: f32,
         force_y: f32,
+        shooter: Option<Entity>,
     ) {
         if let Some(body) = world.write_storage::<RigidBody>().get_mut(entity) {
             body.push(
@@ -270,27 +719,288 @@ impl GameState {
             );
         }
 
+        // Clients only mirror the health told by the server
+        if !self.game_type.is_server() {
+            return;
+        }
+
+        let mut damaged = None;
+
         if let Some(health) = world.write_storage::<Health>().get_mut(entity) {
+            let was_alive = health.is_alive();
+
             health.damage(
                 utils::math::length(force_x, force_y),
                 world.read_resource::<Time>().absolute_time(),
             );
+
+            damaged = Some((health.get(), was_alive && !health.is_alive()));
+        }
+
+        if let Some((health, has_died)) = damaged {
+            let mut net = world.write_resource::<NetResource>();
+
+            net.send_to_relevant(
+                entity,
+                Message::ActorDamage {
+                    id: 0,
+                    entity_id: entity.id(),
+                    health,
+                },
+            );
+
+            if has_died {
+                net.send_to_relevant(
+                    entity,
+                    Message::ActorDeath {
+                        id: 0,
+                        entity_id: entity.id(),
+                    },
+                );
+
+                world
+                    .write_resource::<RosterResource>()
+                    .on_death(entity, shooter);
+            }
         }
     }
 
-    #[allow(clippy::unused_self)]
-    fn on_task_entity_delete(&self, world: &mut World, entity: Entity) {
-        if self.game_type.is_server() {
+    fn on_task_chat_say(&self, world: &World, sender: Entity, text: &str) {
+        let text = sanitize_chat_text(text);
+
+        if text.is_empty() {
+            return;
+        }
+
+        let name = match world.read_resource::<RosterResource>().get_name(sender) {
+            Some(name) => name.to_string(),
+            None => return,
+        };
+
+        log::info!("{} says: {}", name, text);
+
+        world
+            .write_resource::<NetResource>()
+            .send_to_all(Message::Chat {
+                id: 0,
+                sender: name.clone(),
+                text: text.clone(),
+            });
+
+        self.on_task_chat(world, &name, &text);
+    }
+
+    fn on_task_chat(&self, world: &World, sender: &str, text: &str) {
+        if !self.is_headless {
+            let now = world.read_resource::<Time>().absolute_real_time();
+            world
+                .write_resource::<ChatResource>()
+                .add_line(sender, text, now);
+        }
+    }
+
+    fn on_task_console_command(&mut self, world: &mut World, command: &ConsoleCommand) {
+        match *command {
+            ConsoleCommand::Players => {
+                self.print_console(world, &self.format_players(world));
+            }
+            ConsoleCommand::Kick(id) => {
+                self.kick(world, id);
+            }
+            ConsoleCommand::Ban(address) => {
+                let result = world
+                    .write_resource::<NetResource>()
+                    .ban(address, BAN_REASON);
+
+                match result {
+                    Ok(()) => {
+                        self.print_console(world, &format!("{} has been banned", address));
+                    }
+                    Err(error) => {
+                        let text = format!("{} has been banned until restart. {}", address, error);
+                        self.print_console(world, &text);
+                    }
+                }
+            }
+            ConsoleCommand::Say(ref text) => {
+                let text = sanitize_chat_text(text);
+                log::info!("{} says: {}", SERVER_SENDER, text);
+
+                world
+                    .write_resource::<NetResource>()
+                    .send_to_all(Message::Chat {
+                        id: 0,
+                        sender: SERVER_SENDER.to_string(),
+                        text: text.clone(),
+                    });
+
+                self.on_task_chat(world, SERVER_SENDER, &text);
+            }
+            ConsoleCommand::SpawnZombies(count) => {
+                let mut randomizer = rand::thread_rng();
+
+                for _ in 0..count {
+                    let x = randomizer.gen_range(-ZOMBIE_SPAWN_RANGE..ZOMBIE_SPAWN_RANGE);
+                    let y = randomizer.gen_range(-ZOMBIE_SPAWN_RANGE..ZOMBIE_SPAWN_RANGE);
+                    self.spawn_zombie(world, Position::new(x, y, 0.0));
+                }
+
+                self.print_console(world, &format!("Spawned {} zombies", count));
+            }
+            ConsoleCommand::Restart => {
+                self.restart(world);
+                self.print_console(world, "The game has been restarted");
+            }
+            ConsoleCommand::Shutdown => {
+                log::info!("Shutting down");
+                self.is_shutdown = true;
+            }
+        }
+    }
+
+    /// Logs the output of a console command, also showing it in the chat on the host
+    fn print_console(&self, world: &World, text: &str) {
+        log::info!("{}", text);
+
+        for line in text.lines() {
+            self.on_task_chat(world, CONSOLE_SENDER, line);
+        }
+    }
+
+    fn format_players(&self, world: &World) -> String {
+        let roster = world.read_resource::<RosterResource>();
+        let net = world.read_resource::<NetResource>();
+        let mut text = String::new();
+
+        for player in roster.get_players() {
+            let actor = roster.get_actor(player.id);
+            let address;
+
+            if let Some(found) = actor.and_then(|a| net.find_address(a)) {
+                address = found.to_string();
+            } else if actor.is_some() && actor == self.player {
+                address = "host".to_string();
+            } else {
+                address = "dropped".to_string();
+            }
+
+            text.push_str(&format!(
+                "{}. {} - {} - {} / {} - {} ms\n",
+                player.id, player.name, address, player.kills, player.deaths, player.ping,
+            ));
+        }
+
+        if text.is_empty() {
+            text.push_str("No players");
+        }
+
+        return text;
+    }
+
+    fn kick(&self, world: &World, id: u16) {
+        let mut text = format!("No connected player with id {}", id);
+
+        {
+            let roster = world.read_resource::<RosterResource>();
+            let mut net = world.write_resource::<NetResource>();
+
+            if let Some(actor) = roster.get_actor(id) {
+                if let Some(address) = net.find_address(actor) {
+                    net.kick(&address, KICK_REASON);
+                    text = format!(
+                        "{} has been kicked",
+                        roster.get_name(actor).unwrap_or_default(),
+                    );
+                }
+            }
+        }
+
+        self.print_console(world, &text);
+    }
+
+    /// Deletes every actor and projectile, gives players new actors and resets their scores
+    fn restart(&mut self, world: &mut World) {
+        let root = match self.root {
+            Some(root) => root,
+            None => return,
+        };
+
+        let mut deleted = Vec::new();
+        let mut players = Vec::new();
+
+        {
+            let entities = world.entities();
+            let actors = world.read_storage::<Actor>();
+            let roster = world.read_resource::<RosterResource>();
+
+            for (entity, _) in (&entities, &actors).join() {
+                deleted.push(entity);
+            }
+
+            for (entity, _) in (&entities, &world.read_storage::<Projectile>()).join() {
+                deleted.push(entity);
+            }
+
+            for player in roster.get_players() {
+                // Dead players have lost their actors, while spectators have never had any
+                if let Some(actor) = roster
+                    .get_actor(player.id)
+                    .filter(|a| actors.contains(*a) || !entities.is_alive(*a))
+                {
+                    players.push(actor);
+                }
+            }
+        }
+
+        for entity in deleted {
+            self.on_task_entity_delete(world, entity);
+        }
+
+        for old in players {
+            let new = world.entities().create();
+
+            world.create_actor(
+                root,
+                new,
+                ActorType::HUMAN,
+                Position::default(),
+                false,
+                &self.game_type,
+            );
+
+            world
+                .write_resource::<RosterResource>()
+                .replace_actor(old, new);
             world
                 .write_resource::<NetResource>()
-                .send_to_all(Message::EntityDelete {
-                    id: 0,
-                    entity_id: entity.id(),
-                });
+                .replace_entity(old, new);
+
+            if self.player == Some(old) {
+                world.set_actor_player(root, new, &self.game_type);
+                self.player = Some(new);
+            }
         }
 
+        world.write_resource::<RosterResource>().reset_scores();
+        self.spawn_initial_zombies(world);
+    }
+
+    // Clients get the entity deleted by the relevance system
+    #[allow(clippy::unused_self)]
+    fn on_task_entity_delete(&self, world: &mut World, entity: Entity) {
         world.write_resource::<EntityConverter>().remove(entity);
 
+        let ghost = world
+            .read_storage::<Player>()
+            .get(entity)
+            .and_then(|p| p.ghost);
+
+        if let Some(ghost) = ghost {
+            if let Err(error) = world.delete_entity(ghost) {
+                log::error!("Failed to delete a ghost: {}", error);
+            }
+        }
+
         if let Err(error) = world.delete_entity(entity) {
             log::error!("Failed to delete an entity: {}", error);
         }
@@ -300,13 +1010,33 @@ impl GameState {
 impl SimpleState for GameState {
     fn on_start(&mut self, mut data: StateData<GameData>) {
         self.init_world_entities(&mut data.world);
-        utils::ui::set_cursor_visibility(data.world, false);
+
+        if !self.is_headless {
+            utils::ui::set_cursor_visibility(data.world, false);
+
+            data.world.exec(|finder: UiFinder| {
+                self.net_stats = finder.find(NET_STATS_ID);
+                self.scoreboard = finder.find(SCOREBOARD_ID);
+                self.chat = finder.find(CHAT_ID);
+            });
+
+            self.set_chat_visibility(data.world, true);
+        }
+
         data.world.set_state(Some(self.game_type));
     }
 
     fn on_stop(&mut self, data: StateData<GameData>) {
         data.world.set_state(None);
 
+        if self.is_net_stats_visible {
+            self.set_net_stats_visibility(data.world, false);
+            self.is_net_stats_visible = false;
+        }
+
+        self.set_scoreboard_visibility(data.world, false);
+        self.set_chat_visibility(data.world, false);
+
         if let Some(root) = self.root.take() {
             if let Err(error) = data.world.delete_entity(root) {
                 log::error!("Failed to delete the root entity: {}", error);
@@ -316,10 +1046,25 @@ impl SimpleState for GameState {
 
     fn on_pause(&mut self, data: StateData<GameData>) {
         data.world.set_state(None);
+
+        if self.is_net_stats_visible {
+            self.set_net_stats_visibility(data.world, false);
+        }
+
+        self.set_scoreboard_visibility(data.world, false);
+        self.set_chat_visibility(data.world, false);
     }
 
     fn on_resume(&mut self, data: StateData<GameData>) {
-        utils::ui::set_cursor_visibility(data.world, false);
+        if !self.is_headless {
+            utils::ui::set_cursor_visibility(data.world, false);
+        }
+
+        if self.is_net_stats_visible {
+            self.set_net_stats_visibility(data.world, true);
+        }
+
+        self.set_chat_visibility(data.world, true);
         data.world.set_state(Some(self.game_type));
     }
 
@@ -339,11 +1084,39 @@ impl SimpleState for GameState {
             }
         }
 
+        self.update_scoreboard(data.world);
+
+        if self.is_shutdown {
+            if let Some(mut net) = data.world.remove::<NetResource>() {
+                net.leave(SHUTDOWN_REASON);
+            }
+
+            if self.is_headless {
+                return Trans::Quit;
+            } else {
+                return Trans::Replace(Box::new(HomeState::new(true)));
+            }
+        }
+
+        if let Some(reason) = self.disconnect_reason.take() {
+            return Trans::Replace(Box::new(NoticeState::new(
+                DISCONNECTED_TITLE,
+                reason,
+                Wallpaper::Disconnect,
+                || Trans::Replace(Box::new(HomeState::new(true))),
+            )));
+        }
+
         return Trans::None;
     }
 
     fn handle_event(&mut self, data: StateData<GameData>, event: StateEvent) -> SimpleTrans {
         if let StateEvent::Window(event) = event {
+            if self.chat.is_some() && data.world.read_resource::<ChatResource>().is_typing() {
+                self.on_chat_event(data.world, &event);
+                return Trans::None;
+            }
+
             let mut cursor = data.world.write_resource::<HideCursor>();
 
             match event {
@@ -381,8 +1154,86 @@ impl SimpleState for GameState {
             if cursor.hide && is_key_down(&event, VirtualKeyCode::Tab) {
                 cursor.hide = false;
             }
+
+            if let Some(spectator) = self.spectator {
+                if is_key_down(&event, SPECTATOR_CYCLE_KEY) {
+                    Self::cycle_spectator_target(data.world, spectator);
+                }
+
+                if is_key_down(&event, SPECTATOR_PLAY_KEY) {
+                    data.world
+                        .write_resource::<NetResource>()
+                        .send_to_all(Message::PlayRequest { id: 0 });
+                }
+            }
+
+            if self.chat.is_some() && is_key_down(&event, CHAT_KEY) {
+                data.world.write_resource::<ChatResource>().open_input();
+                cursor.hide = false;
+            }
+
+            if is_key_down(&event, VirtualKeyCode::F3) {
+                self.is_net_stats_visible = !self.is_net_stats_visible;
+                self.set_net_stats_visibility(data.world, self.is_net_stats_visible);
+            }
+
+            if let Some((SCOREBOARD_KEY, state)) = get_key(&event) {
+                let is_pressed = state == ElementState::Pressed;
+
+                // Skip repeated presses of the held key
+                if is_pressed != self.scoreboard_players.is_some() {
+                    self.set_scoreboard_visibility(data.world, is_pressed);
+                }
+            }
         }
 
         return Trans::None;
     }
 }
+
+/// The client has joined already, has been rejected or has been dropped since then
+fn is_join_skipped(net: &NetResource, address: &SocketAddr) -> bool {
+    return net
+        .connections
+        .get(address)
+        .map_or(true, |c| c.attached_entity.is_some() || !c.is_connected());
+}
+
+fn check_join(protocol_version: u16, build: &str, is_banned: bool) -> Result<(), String> {
+    if is_banned {
+        return Err("You are banned from this server".to_string());
+    }
+
+    if protocol_version != PROTOCOL_VERSION {
+        return Err(format!(
+            "Incompatible protocol version {}, the server has {}",
+            protocol_version, PROTOCOL_VERSION,
+        ));
+    }
+
+    if build != BUILD_HASH {
+        return Err(format!(
+            "Different game build {}, the server has {}",
+            build, BUILD_HASH,
+        ));
+    }
+
+    return Ok(());
+}
+
+/// Lists players from the best one
+fn format_scoreboard(players: &[RosterPlayer]) -> String {
+    let mut sorted = players.iter().collect::<Vec<&RosterPlayer>>();
+    sorted.sort_by(|a, b| b.kills.cmp(&a.kills).then(a.deaths.cmp(&b.deaths)));
+
+    let mut text = "Player - kills / deaths - ping\n\n".to_string();
+
+    for player in sorted {
+        text.push_str(&format!(
+            "{} - {} / {} - {} ms\n",
+            player.name, player.kills, player.deaths, player.ping,
+        ));
+    }
+
+    return text;
+}
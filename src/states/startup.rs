@@ -1,21 +1,31 @@
+use crate::models::GameType;
 use crate::resources::GameStatus;
+use crate::resources::NetRecording;
 use crate::resources::SpriteResource;
 use crate::resources::WallpaperResource;
 use crate::states::ui::HomeState;
+use crate::states::ui::LoadingState;
 use amethyst::assets::Completion;
 use amethyst::assets::ProgressCounter;
 use amethyst::prelude::*;
 use amethyst::ui::UiCreator;
 use amethyst::window::Window;
+use std::net::SocketAddr;
 
 pub struct StartupState {
     progress: ProgressCounter,
+    game_type: Option<GameType>,
+    replay: Option<(SocketAddr, NetRecording)>,
 }
 
 impl StartupState {
-    pub fn new() -> Self {
+    /// The game will be hosted or joined right after startup if `game_type` is specified, or the
+    /// recording of the session with the server will be played back if `replay` is
+    pub fn new(game_type: Option<GameType>, replay: Option<(SocketAddr, NetRecording)>) -> Self {
         return Self {
             progress: ProgressCounter::new(),
+            game_type,
+            replay,
         };
     }
 }
@@ -23,10 +33,14 @@ impl StartupState {
 impl SimpleState for StartupState {
     fn on_start(&mut self, data: StateData<GameData>) {
         data.world.exec(|mut creator: UiCreator| {
+            creator.create("ui/chat.ron", &mut self.progress);
             creator.create("ui/confirm.ron", &mut self.progress);
             creator.create("ui/home.ron", &mut self.progress);
             creator.create("ui/loading.ron", &mut self.progress);
+            creator.create("ui/net_stats.ron", &mut self.progress);
             creator.create("ui/new_game.ron", &mut self.progress);
+            creator.create("ui/notice.ron", &mut self.progress);
+            creator.create("ui/scoreboard.ron", &mut self.progress);
         });
 
         data.world
@@ -44,7 +58,25 @@ impl SimpleState for StartupState {
             Completion::Complete => {
                 enable_fullscreen_mode(data.world);
                 complete_startup(data.world);
-                return Trans::Switch(Box::new(HomeState::new(true)));
+
+                let home = Trans::Switch(Box::new(HomeState::new(true)));
+
+                if let Some((server_address, recording)) = self.replay.take() {
+                    return Trans::Sequence(vec![
+                        home,
+                        Trans::Push(Box::new(LoadingState::new_replay(
+                            server_address,
+                            recording,
+                        ))),
+                    ]);
+                } else if let Some(game_type) = self.game_type {
+                    return Trans::Sequence(vec![
+                        home,
+                        Trans::Push(Box::new(LoadingState::new(game_type))),
+                    ]);
+                } else {
+                    return home;
+                }
             }
             Completion::Failed => {
                 log::error!("Failed to load assets");
@@ -20,11 +20,15 @@ pub struct ConfirmState {
     button_yes: Option<Entity>,
     button_no: Option<Entity>,
     wallpaper: Wallpaper,
-    on_confirm: fn() -> SimpleTrans,
+    on_confirm: fn(&mut World) -> SimpleTrans,
 }
 
 impl ConfirmState {
-    pub fn new(title: &'static str, wallpaper: Wallpaper, on_confirm: fn() -> SimpleTrans) -> Self {
+    pub fn new(
+        title: &'static str,
+        wallpaper: Wallpaper,
+        on_confirm: fn(&mut World) -> SimpleTrans,
+    ) -> Self {
         return Self {
             title,
             root: None,
@@ -64,7 +68,7 @@ impl SimpleState for ConfirmState {
         self.set_visibility(data.world, false);
     }
 
-    fn handle_event(&mut self, _: StateData<GameData>, event: StateEvent) -> SimpleTrans {
+    fn handle_event(&mut self, data: StateData<GameData>, event: StateEvent) -> SimpleTrans {
         match event {
             StateEvent::Window(event) => {
                 if is_key_down(&event, VirtualKeyCode::Escape) {
@@ -76,7 +80,7 @@ impl SimpleState for ConfirmState {
                 target,
             }) => {
                 if Some(target) == self.button_yes {
-                    return (self.on_confirm)();
+                    return (self.on_confirm)(data.world);
                 }
 
                 if Some(target) == self.button_no {
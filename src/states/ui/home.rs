@@ -1,3 +1,4 @@
+use crate::resources::NetResource;
 use crate::resources::Wallpaper;
 use crate::states::ui::ConfirmState;
 use crate::states::ui::NewGameState;
@@ -19,7 +20,9 @@ const BUTTON_SETTINGS_ID: &str = "home.settings";
 const BUTTON_HELP_ID: &str = "home.help";
 const BUTTON_QUIT_ID: &str = "home.quit";
 const DISCONNECTION_TITLE: &str = "Are you sure you want to disconnect?";
+const DISCONNECTION_REASON: &str = "Disconnected by the player";
 const QUIT_TITLE: &str = "Are you sure you want to quit?";
+const QUIT_REASON: &str = "The player has quit the game";
 
 pub struct HomeState {
     is_root: bool,
@@ -108,7 +111,10 @@ impl SimpleState for HomeState {
                     return Trans::Push(Box::new(ConfirmState::new(
                         DISCONNECTION_TITLE,
                         Wallpaper::Disconnect,
-                        || Trans::Replace(Box::new(Self::new(true))),
+                        |world| {
+                            leave_game(world, DISCONNECTION_REASON);
+                            return Trans::Replace(Box::new(Self::new(true)));
+                        },
                     )));
                 }
 
@@ -116,7 +122,10 @@ impl SimpleState for HomeState {
                     return Trans::Push(Box::new(ConfirmState::new(
                         QUIT_TITLE,
                         Wallpaper::Quit,
-                        || Trans::Quit,
+                        |world| {
+                            leave_game(world, QUIT_REASON);
+                            return Trans::Quit;
+                        },
                     )));
                 }
             }
@@ -132,3 +141,9 @@ impl UiState for HomeState {
         return self.root;
     }
 }
+
+fn leave_game(world: &mut World, reason: &str) {
+    if let Some(mut net) = world.remove::<NetResource>() {
+        net.leave(reason);
+    }
+}
@@ -1,9 +1,15 @@
 use crate::models::GameType;
+use crate::models::NetConfig;
+use crate::resources::ChatResource;
 use crate::resources::EntityConverter;
 use crate::resources::GameTask;
 use crate::resources::GameTaskResource;
+use crate::resources::NetRecording;
 use crate::resources::NetResource;
 use crate::resources::PositionUpdateResource;
+use crate::resources::PredictionResource;
+use crate::resources::RosterResource;
+use crate::resources::SnapshotHistory;
 use crate::resources::Wallpaper;
 use crate::states::ui::UiState;
 use crate::states::GameState;
@@ -19,6 +25,7 @@ use amethyst::ui::UiEvent;
 use amethyst::ui::UiEventType;
 use amethyst::ui::UiFinder;
 use amethyst::winit::VirtualKeyCode;
+use std::net::SocketAddr;
 use std::time::Duration;
 
 const ROOT_ID: &str = "loading";
@@ -32,6 +39,8 @@ pub struct LoadingState {
     button_cancel: Option<Entity>,
     dots_timer: Timer,
     dots_count: u8,
+    is_failed: bool,
+    replay: Option<NetRecording>,
 }
 
 impl LoadingState {
@@ -42,6 +51,16 @@ impl LoadingState {
             button_cancel: None,
             dots_timer: Timer::new(DOTS_INTERVAL),
             dots_count: 1,
+            is_failed: false,
+            replay: None,
+        };
+    }
+
+    /// Plays the recording back instead of joining the server
+    pub fn new_replay(server_address: SocketAddr, recording: NetRecording) -> Self {
+        return Self {
+            replay: Some(recording),
+            ..Self::new(GameType::Client(server_address))
         };
     }
 }
@@ -57,19 +76,36 @@ impl SimpleState for LoadingState {
         self.set_visibility(data.world, true);
 
         data.world.remove::<NetResource>();
+        data.world.insert(ChatResource::new());
         data.world.insert(DebugLines::new());
         data.world.insert(EntityConverter::new());
         data.world.insert(GameTaskResource::new());
         data.world.insert(PositionUpdateResource::new());
+        data.world.insert(PredictionResource::new());
+        data.world.insert(RosterResource::new());
+        data.world.insert(SnapshotHistory::new());
 
-        #[allow(clippy::unwrap_used)] // TODO: Resolve
-        match self.game_type {
-            GameType::Server(port) => {
-                data.world.insert(NetResource::new_as_server(port).unwrap());
-            }
+        let config = data.world.read_resource::<NetConfig>().clone();
+
+        let net = match self.game_type {
+            GameType::Server(port) => NetResource::new_as_server(port, config),
             GameType::Client(address) => {
-                data.world
-                    .insert(NetResource::new_as_client(address).unwrap());
+                if let Some(recording) = self.replay.take() {
+                    NetResource::new_as_replay(address, recording, config)
+                } else {
+                    NetResource::new_as_client(address, config)
+                }
+            }
+        };
+
+        match net {
+            Ok(net) => {
+                data.world.insert(net);
+            }
+            Err(error) => {
+                log::error!("Failed to start networking: {}", error);
+                self.is_failed = true;
+                utils::ui::set_text(data.world, DOTS_ID, error);
             }
         }
 
@@ -93,7 +129,12 @@ impl SimpleState for LoadingState {
     }
 
     fn update(&mut self, data: &mut StateData<GameData>) -> SimpleTrans {
+        if self.is_failed {
+            return Trans::None;
+        }
+
         let mut is_ready = false;
+        let mut failure = None;
 
         match self.game_type {
             GameType::Server(..) => {
@@ -101,9 +142,16 @@ impl SimpleState for LoadingState {
             }
             GameType::Client(..) => {
                 for task in data.world.read_resource::<GameTaskResource>().iter() {
-                    if let GameTask::Start = *task {
-                        is_ready = true;
-                        break;
+                    match *task {
+                        GameTask::Start => {
+                            is_ready = true;
+                            break;
+                        }
+                        GameTask::Disconnected(ref reason) => {
+                            failure = Some(reason.clone());
+                            break;
+                        }
+                        _ => {}
                     }
                 }
             }
@@ -113,6 +161,12 @@ impl SimpleState for LoadingState {
             return Trans::Replace(Box::new(GameState::new(self.game_type)));
         }
 
+        if let Some(failure) = failure {
+            self.is_failed = true;
+            utils::ui::set_text(data.world, DOTS_ID, failure);
+            return Trans::None;
+        }
+
         let now = data.world.read_resource::<Time>().absolute_real_time();
 
         if self.dots_timer.next_if_done(now) {
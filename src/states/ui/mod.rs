@@ -2,11 +2,13 @@ mod confirm;
 mod home;
 mod loading;
 mod new_game;
+mod notice;
 
 pub use self::confirm::*;
 pub use self::home::*;
 pub use self::loading::*;
 pub use self::new_game::*;
+pub use self::notice::*;
 use crate::resources::Wallpaper;
 use crate::resources::WallpaperResource;
 use crate::utils;
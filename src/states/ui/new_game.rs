@@ -1,5 +1,10 @@
+use crate::data::BUILD_HASH;
 use crate::models::GameType;
+use crate::models::NetConfig;
+use crate::resources::DiscoveredServer;
+use crate::resources::LanDiscovery;
 use crate::resources::Wallpaper;
+use crate::resources::PROTOCOL_VERSION;
 use crate::states::ui::LoadingState;
 use crate::states::ui::UiState;
 use crate::utils;
@@ -16,14 +21,27 @@ const ROOT_ID: &str = "new_game";
 const BUTTON_HOST_ID: &str = "new_game.host";
 const BUTTON_JOIN_ID: &str = "new_game.join";
 const BUTTON_BACK_ID: &str = "new_game.back";
+const INPUT_NAME_ID: &str = "new_game.name";
 const INPUT_IP_ID: &str = "new_game.ip";
 const INPUT_PORT_ID: &str = "new_game.port";
+const BUTTON_SERVER_IDS: [&str; 3] = [
+    "new_game.server_0",
+    "new_game.server_1",
+    "new_game.server_2",
+];
+const BUTTON_SERVER_TEXT_IDS: [&str; 3] = [
+    "new_game.server_0_btn_txt",
+    "new_game.server_1_btn_txt",
+    "new_game.server_2_btn_txt",
+];
 
 pub struct NewGameState {
     root: Option<Entity>,
     button_host: Option<Entity>,
     button_join: Option<Entity>,
     button_back: Option<Entity>,
+    button_servers: [Option<Entity>; 3],
+    discovery: Option<LanDiscovery>,
 }
 
 impl NewGameState {
@@ -33,9 +51,48 @@ impl NewGameState {
             button_host: None,
             button_join: None,
             button_back: None,
+            button_servers: [None; 3],
+            discovery: None,
         };
     }
 
+    fn show_servers(&self, world: &World) {
+        let servers = self
+            .discovery
+            .as_ref()
+            .map_or(&[][..], LanDiscovery::get_servers);
+
+        for (i, (&id, &text_id)) in BUTTON_SERVER_IDS
+            .iter()
+            .zip(BUTTON_SERVER_TEXT_IDS.iter())
+            .enumerate()
+        {
+            let text;
+            let is_available;
+
+            if let Some(server) = servers.get(i) {
+                text = format_server(server);
+                is_available = is_compatible(server);
+            } else if i == 0 && servers.is_empty() {
+                text = "Looking for servers...".to_string();
+                is_available = false;
+            } else {
+                text = String::new();
+                is_available = false;
+            }
+
+            utils::ui::set_text(world, text_id, text);
+            utils::ui::set_button_availability(world, id, is_available);
+        }
+    }
+
+    /// Remembers the entered name to join games with
+    fn apply_input_name(world: &World) {
+        if let Some(name) = utils::ui::fetch_text(world, INPUT_NAME_ID) {
+            world.write_resource::<NetConfig>().player_name = name;
+        }
+    }
+
     fn parse_input_ip(world: &World) -> Result<String, &str> {
         if let Some(ip) = utils::ui::fetch_text(world, INPUT_IP_ID) {
             return Ok(ip);
@@ -69,8 +126,27 @@ impl SimpleState for NewGameState {
             self.button_host = finder.find(BUTTON_HOST_ID);
             self.button_join = finder.find(BUTTON_JOIN_ID);
             self.button_back = finder.find(BUTTON_BACK_ID);
+
+            for (button, id) in self.button_servers.iter_mut().zip(BUTTON_SERVER_IDS.iter()) {
+                *button = finder.find(id);
+            }
         });
 
+        let player_name = data.world.read_resource::<NetConfig>().player_name.clone();
+        utils::ui::set_text(data.world, INPUT_NAME_ID, player_name);
+
+        let discovery_port = data.world.read_resource::<NetConfig>().discovery_port;
+
+        match LanDiscovery::new(discovery_port) {
+            Ok(discovery) => {
+                self.discovery = Some(discovery);
+            }
+            Err(error) => {
+                log::warn!("Failed to look for servers on the local network: {}", error);
+            }
+        }
+
+        self.show_servers(data.world);
         self.set_wallpaper(data.world, Wallpaper::Play);
         self.set_visibility(data.world, true);
     }
@@ -87,9 +163,19 @@ impl SimpleState for NewGameState {
         self.button_host = None;
         self.button_join = None;
         self.button_back = None;
+        self.button_servers = [None; 3];
+        self.discovery = None;
         self.set_visibility(data.world, false);
     }
 
+    fn update(&mut self, data: &mut StateData<GameData>) -> SimpleTrans {
+        if self.discovery.as_mut().map_or(false, LanDiscovery::update) {
+            self.show_servers(data.world);
+        }
+
+        return Trans::None;
+    }
+
     fn handle_event(&mut self, data: StateData<GameData>, event: StateEvent) -> SimpleTrans {
         match event {
             StateEvent::Window(event) => {
@@ -101,6 +187,10 @@ impl SimpleState for NewGameState {
                 event_type: UiEventType::Click,
                 target,
             }) => {
+                if Some(target) != self.button_back {
+                    Self::apply_input_name(data.world);
+                }
+
                 if Some(target) == self.button_host {
                     match Self::parse_input_port(data.world) {
                         Ok(port) => {
@@ -128,6 +218,15 @@ impl SimpleState for NewGameState {
                 if Some(target) == self.button_back {
                     return Trans::Pop;
                 }
+
+                if let Some(i) = self.button_servers.iter().position(|b| *b == Some(target)) {
+                    if let Some(server) =
+                        self.discovery.as_ref().and_then(|d| d.get_servers().get(i))
+                    {
+                        let game_type = GameType::Client(server.address);
+                        return Trans::Push(Box::new(LoadingState::new(game_type)));
+                    }
+                }
             }
             _ => {}
         }
@@ -141,3 +240,20 @@ impl UiState for NewGameState {
         return self.root;
     }
 }
+
+fn is_compatible(server: &DiscoveredServer) -> bool {
+    return server.info.protocol_version == PROTOCOL_VERSION && server.info.build == BUILD_HASH;
+}
+
+fn format_server(server: &DiscoveredServer) -> String {
+    let mut text = format!(
+        "{} - {}, {} players",
+        server.info.name, server.info.map, server.info.players,
+    );
+
+    if !is_compatible(server) {
+        text.push_str(" (other version)");
+    }
+
+    return text;
+}
@@ -0,0 +1,97 @@
+use crate::resources::Wallpaper;
+use crate::states::ui::UiState;
+use crate::utils;
+use amethyst::ecs::Entity;
+use amethyst::input::is_key_down;
+use amethyst::prelude::*;
+use amethyst::ui::UiEvent;
+use amethyst::ui::UiEventType;
+use amethyst::ui::UiFinder;
+use amethyst::winit::VirtualKeyCode;
+
+const ROOT_ID: &str = "notice";
+const LABEL_TITLE_ID: &str = "notice.title";
+const LABEL_MESSAGE_ID: &str = "notice.message";
+const BUTTON_OK_ID: &str = "notice.ok";
+
+pub struct NoticeState {
+    title: &'static str,
+    message: String,
+    root: Option<Entity>,
+    button_ok: Option<Entity>,
+    wallpaper: Wallpaper,
+    on_close: fn() -> SimpleTrans,
+}
+
+impl NoticeState {
+    pub fn new(
+        title: &'static str,
+        message: String,
+        wallpaper: Wallpaper,
+        on_close: fn() -> SimpleTrans,
+    ) -> Self {
+        return Self {
+            title,
+            message,
+            root: None,
+            button_ok: None,
+            wallpaper,
+            on_close,
+        };
+    }
+}
+
+impl SimpleState for NoticeState {
+    fn on_start(&mut self, data: StateData<GameData>) {
+        data.world.exec(|finder: UiFinder| {
+            self.root = finder.find(ROOT_ID);
+            self.button_ok = finder.find(BUTTON_OK_ID);
+        });
+
+        utils::ui::set_text(data.world, LABEL_TITLE_ID, self.title.to_string());
+        utils::ui::set_text(data.world, LABEL_MESSAGE_ID, self.message.clone());
+
+        self.set_wallpaper(data.world, self.wallpaper);
+        self.set_visibility(data.world, true);
+    }
+
+    fn on_pause(&mut self, data: StateData<GameData>) {
+        self.set_visibility(data.world, false);
+    }
+
+    fn on_resume(&mut self, data: StateData<GameData>) {
+        self.set_visibility(data.world, true);
+    }
+
+    fn on_stop(&mut self, data: StateData<GameData>) {
+        self.button_ok = None;
+        self.set_visibility(data.world, false);
+    }
+
+    fn handle_event(&mut self, _: StateData<GameData>, event: StateEvent) -> SimpleTrans {
+        match event {
+            StateEvent::Window(event) => {
+                if is_key_down(&event, VirtualKeyCode::Escape) {
+                    return (self.on_close)();
+                }
+            }
+            StateEvent::Ui(UiEvent {
+                event_type: UiEventType::Click,
+                target,
+            }) => {
+                if Some(target) == self.button_ok {
+                    return (self.on_close)();
+                }
+            }
+            _ => {}
+        }
+
+        return Trans::None;
+    }
+}
+
+impl UiState for NoticeState {
+    fn get_root(&self) -> Option<Entity> {
+        return self.root;
+    }
+}
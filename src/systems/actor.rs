@@ -1,5 +1,6 @@
 use crate::components::Actor;
 use crate::components::ActorActions;
+use crate::components::ActorType;
 use crate::components::RigidBody;
 use crate::utils;
 use amethyst::core::math::Vector3;
@@ -25,36 +26,51 @@ impl<'a> System<'a> for ActorSystem {
         for (actor, body, transform) in (&actors, &mut bodies, &mut transforms).join() {
             transform.rotate_2d(actor.rotation);
 
-            if actor.actions.is_empty() {
-                continue;
-            }
-
-            let mut movement = Vector3::new(0.0, 0.0, 0.0);
+            move_actor(
+                actor.actions,
+                actor.actor_type,
+                transform,
+                body,
+                time.delta_seconds(),
+            );
+        }
+    }
+}
 
-            if actor.actions.contains(ActorActions::MOVEMENT_FORWARD) {
-                movement.y += 1.0;
-            }
+/// Pushes the body according to movement actions
+pub fn move_actor(
+    actions: ActorActions,
+    actor_type: &ActorType,
+    transform: &Transform,
+    body: &mut RigidBody,
+    delta: f32,
+) {
+    if actions.is_empty() {
+        return;
+    }
 
-            if actor.actions.contains(ActorActions::MOVEMENT_BACKWARD) {
-                movement.y -= 1.0;
-            }
+    let mut movement = Vector3::new(0.0, 0.0, 0.0);
 
-            if actor.actions.contains(ActorActions::MOVEMENT_LEFTWARD) {
-                movement.x -= 1.0;
-            }
+    if actions.contains(ActorActions::MOVEMENT_FORWARD) {
+        movement.y += 1.0;
+    }
 
-            if actor.actions.contains(ActorActions::MOVEMENT_RIGHTWARD) {
-                movement.x += 1.0;
-            }
+    if actions.contains(ActorActions::MOVEMENT_BACKWARD) {
+        movement.y -= 1.0;
+    }
 
-            movement = transform.rotation()
-                * normalize_movement(movement)
-                * actor.actor_type.movement_velocity
-                * time.delta_seconds();
+    if actions.contains(ActorActions::MOVEMENT_LEFTWARD) {
+        movement.x -= 1.0;
+    }
 
-            body.push(movement.x, movement.y, 0.0, false, true);
-        }
+    if actions.contains(ActorActions::MOVEMENT_RIGHTWARD) {
+        movement.x += 1.0;
     }
+
+    movement =
+        transform.rotation() * normalize_movement(movement) * actor_type.movement_velocity * delta;
+
+    body.push(movement.x, movement.y, 0.0, false, true);
 }
 
 fn normalize_movement(mut movement: Vector3<f32>) -> Vector3<f32> {
@@ -0,0 +1,76 @@
+use crate::resources::ChatResource;
+use crate::resources::CHAT_LINES_MAX;
+use crate::utils::DurationExt;
+use amethyst::core::timing::Time;
+use amethyst::ecs::Join;
+use amethyst::ecs::Read;
+use amethyst::ecs::ReadStorage;
+use amethyst::ecs::System;
+use amethyst::ecs::WriteStorage;
+use amethyst::ui::UiText;
+use amethyst::ui::UiTransform;
+use std::time::Duration;
+
+const LINE_IDS: [&str; CHAT_LINES_MAX] = [
+    "chat.line_0",
+    "chat.line_1",
+    "chat.line_2",
+    "chat.line_3",
+    "chat.line_4",
+];
+
+const INPUT_ID: &str = "chat.input";
+
+/// How long a line stays on screen, including its fade-out
+const LINE_LIFETIME: Duration = Duration::from_secs(10);
+const LINE_FADE: Duration = Duration::from_secs(2);
+
+/// Shows recent chat lines fading out with time, and the text being typed
+pub struct ChatSystem;
+
+impl<'a> System<'a> for ChatSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        Read<'a, ChatResource>,
+        ReadStorage<'a, UiTransform>,
+        WriteStorage<'a, UiText>,
+    );
+
+    fn run(&mut self, (time, chat, transforms, mut texts): Self::SystemData) {
+        let now = time.absolute_real_time();
+        let lines = chat.get_lines();
+        // The newest line goes to the bottom label
+        let offset = CHAT_LINES_MAX.saturating_sub(lines.len());
+
+        for (transform, text) in (&transforms, &mut texts).join() {
+            if transform.id == INPUT_ID {
+                text.text = chat
+                    .get_input()
+                    .map_or_else(String::new, |input| format!("> {}_", input));
+
+                continue;
+            }
+
+            if let Some(i) = LINE_IDS.iter().position(|id| *id == transform.id) {
+                match i.checked_sub(offset).and_then(|i| lines.get(i)) {
+                    Some(line) => {
+                        text.text.clone_from(&line.text);
+
+                        if chat.is_typing() {
+                            text.color[3] = 1.0;
+                        } else {
+                            text.color[3] = calc_alpha(now.sub_safely(line.time));
+                        }
+                    }
+                    None => {
+                        text.text.clear();
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn calc_alpha(age: Duration) -> f32 {
+    return 1.0 - age.get_progress(LINE_LIFETIME.sub_safely(LINE_FADE), LINE_LIFETIME);
+}
@@ -0,0 +1,63 @@
+use crate::models::ConsoleCommand;
+use crate::resources::GameTask;
+use crate::resources::GameTaskResource;
+use amethyst::ecs::System;
+use amethyst::ecs::Write;
+use std::io;
+use std::io::BufRead;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::thread;
+
+/// Takes commands of the server admin from the standard input, which is read on its own thread
+/// so it doesn't block the game
+pub struct ConsoleSystem {
+    lines: Receiver<String>,
+}
+
+impl ConsoleSystem {
+    pub fn new() -> Self {
+        let (sender, lines) = mpsc::channel();
+
+        thread::spawn(move || {
+            let stdin = io::stdin();
+
+            for line in stdin.lock().lines() {
+                match line {
+                    Ok(line) => {
+                        if sender.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(error) => {
+                        log::error!("Failed to read the console: {}", error);
+                        break;
+                    }
+                }
+            }
+        });
+
+        return Self { lines };
+    }
+}
+
+impl<'a> System<'a> for ConsoleSystem {
+    type SystemData = Write<'a, GameTaskResource>;
+
+    fn run(&mut self, mut tasks: Self::SystemData) {
+        for line in self.lines.try_iter() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match ConsoleCommand::parse(&line) {
+                Ok(command) => {
+                    tasks.push(GameTask::ConsoleCommand(command));
+                }
+                Err(error) => {
+                    log::warn!("{}", error);
+                }
+            }
+        }
+    }
+}
@@ -1,11 +1,14 @@
 mod actor;
 mod ai;
 mod camera;
+mod chat;
+mod console;
 mod health;
 pub mod net;
 mod physics;
 mod player;
 mod projectile;
+mod spectator;
 mod terrain;
 mod ui_resize;
 mod weapon;
@@ -13,10 +16,13 @@ mod weapon;
 pub use self::actor::*;
 pub use self::ai::*;
 pub use self::camera::*;
+pub use self::chat::*;
+pub use self::console::*;
 pub use self::health::*;
 pub use self::physics::*;
 pub use self::player::*;
 pub use self::projectile::*;
+pub use self::spectator::*;
 pub use self::terrain::*;
 pub use self::ui_resize::*;
 pub use self::weapon::*;
@@ -1,18 +1,92 @@
+use crate::components::Actor;
+use crate::components::ActorActions;
+use crate::resources::EntityConverter;
+use crate::resources::GameTask;
+use crate::resources::GameTaskResource;
+use crate::resources::NetConnectionStatus;
 use crate::resources::NetResource;
+use crate::resources::PositionUpdateResource;
+use crate::resources::PredictionResource;
+use crate::resources::RosterResource;
+use crate::resources::SnapshotHistory;
 use amethyst::ecs::System;
 use amethyst::ecs::Write;
+use amethyst::ecs::WriteStorage;
 
 pub struct ConnectionUpdateSystem;
 
 impl<'a> System<'a> for ConnectionUpdateSystem {
-    type SystemData = (Option<Write<'a, NetResource>>,);
+    type SystemData = (
+        Write<'a, GameTaskResource>,
+        Write<'a, RosterResource>,
+        Write<'a, EntityConverter>,
+        Write<'a, SnapshotHistory>,
+        Write<'a, PredictionResource>,
+        Write<'a, PositionUpdateResource>,
+        WriteStorage<'a, Actor>,
+        Option<Write<'a, NetResource>>,
+    );
 
-    fn run(&mut self, (net,): Self::SystemData) {
+    fn run(
+        &mut self,
+        (
+            mut tasks,
+            mut roster,
+            mut converter,
+            mut snapshots,
+            mut prediction,
+            mut position_updates,
+            mut actors,
+            net,
+        ): Self::SystemData,
+    ) {
         let mut net = match net {
             Some(net) => net,
             None => return,
         };
 
-        net.update_connections();
+        let is_server = net.is_server();
+        let is_replay = net.is_replay();
+
+        for (address, connection) in net.update_connections() {
+            if let Some(entity) = connection.attached_entity {
+                if net.keep_session(&connection) {
+                    // Stand still until the client rejoins
+                    if let Some(actor) = actors.get_mut(entity) {
+                        actor.actions = ActorActions::empty();
+                    }
+                } else {
+                    tasks.push(GameTask::EntityDelete(entity));
+                    roster.remove(entity);
+                }
+            }
+
+            if !is_server {
+                // There's no server to rejoin while replaying
+                if let Some(session_token) = connection.session_token.filter(|_| !is_replay) {
+                    log::warn!("Rejoining {}", address);
+                    net.rejoin(address, session_token);
+
+                    // The server may have deleted something meanwhile, so start over with
+                    // what it sends after the rejoin
+                    for entity in converter.take_entities() {
+                        tasks.push(GameTask::EntityDelete(entity));
+                    }
+
+                    *snapshots = SnapshotHistory::new();
+                    *prediction = PredictionResource::new();
+                    position_updates.clear();
+                } else if let NetConnectionStatus::Disconnected(ref reason) =
+                    *connection.get_status()
+                {
+                    tasks.push(GameTask::Disconnected(reason.clone()));
+                }
+            }
+        }
+
+        for entity in net.take_expired_sessions() {
+            tasks.push(GameTask::EntityDelete(entity));
+            roster.remove(entity);
+        }
     }
 }
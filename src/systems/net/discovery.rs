@@ -0,0 +1,103 @@
+use crate::components::Player;
+use crate::data::BUILD_HASH;
+use crate::data::MAP_NAME;
+use crate::resources::DiscoveryResponder;
+use crate::resources::NetResource;
+use crate::resources::ServerInfo;
+use crate::resources::PROTOCOL_VERSION;
+use amethyst::ecs::Join;
+use amethyst::ecs::Read;
+use amethyst::ecs::ReadStorage;
+use amethyst::ecs::System;
+use std::convert::TryFrom;
+
+/// Answers players looking for servers on the local network while a game is hosted
+pub struct DiscoverySystem {
+    responder: Option<DiscoveryResponder>,
+    /// Don't retry to listen to the port every frame if it's taken
+    failed_port: Option<u16>,
+    id: u64,
+}
+
+impl DiscoverySystem {
+    pub const fn new() -> Self {
+        return Self {
+            responder: None,
+            failed_port: None,
+            id: 0,
+        };
+    }
+
+    fn listen(&mut self, port: u16) {
+        match DiscoveryResponder::new(port) {
+            Ok(responder) => {
+                self.responder = Some(responder);
+                self.id = rand::random();
+            }
+            Err(error) => {
+                log::warn!("Failed to listen to discovery on port {}: {}", port, error);
+                self.failed_port = Some(port);
+            }
+        }
+    }
+}
+
+impl<'a> System<'a> for DiscoverySystem {
+    type SystemData = (Option<Read<'a, NetResource>>, ReadStorage<'a, Player>);
+
+    fn run(&mut self, (net, players): Self::SystemData) {
+        let net = match net {
+            Some(ref net) if net.is_server() && net.get_config().discoverable => net,
+            _ => {
+                self.responder = None;
+                self.failed_port = None;
+                return;
+            }
+        };
+
+        let discovery_port = net.get_config().discovery_port;
+
+        if self.responder.is_none() && self.failed_port != Some(discovery_port) {
+            self.listen(discovery_port);
+        }
+
+        let responder = match self.responder.as_ref() {
+            Some(responder) => responder,
+            None => return,
+        };
+
+        let requesters = responder.take_requests();
+
+        if requesters.is_empty() {
+            return;
+        }
+
+        let port = match net.socket.local_addr() {
+            Ok(address) => address.port(),
+            Err(..) => return,
+        };
+
+        let clients = net
+            .connections
+            .values()
+            .filter(|c| c.attached_entity.is_some())
+            .count();
+
+        // The host's own player if it isn't a dedicated server
+        let hosts = players.join().count();
+
+        let info = ServerInfo {
+            id: self.id,
+            name: net.get_config().server_name.clone(),
+            players: u16::try_from(clients + hosts).unwrap_or(u16::MAX),
+            map: MAP_NAME.to_string(),
+            protocol_version: PROTOCOL_VERSION,
+            build: BUILD_HASH.to_string(),
+            port,
+        };
+
+        for requester in requesters {
+            responder.respond(requester, info.clone());
+        }
+    }
+}
@@ -3,6 +3,7 @@ use crate::components::ActorActions;
 use crate::components::Player;
 use crate::resources::Message;
 use crate::resources::NetResource;
+use crate::resources::PredictionResource;
 use crate::utils::DurationExt;
 use amethyst::core::timing::Time;
 use amethyst::core::transform::Transform;
@@ -48,9 +49,10 @@ impl<'a> System<'a> for InputSendSystem {
         ReadStorage<'a, Player>,
         ReadStorage<'a, Transform>,
         Option<Write<'a, NetResource>>,
+        Write<'a, PredictionResource>,
     );
 
-    fn run(&mut self, (time, actors, players, transforms, net): Self::SystemData) {
+    fn run(&mut self, (time, actors, players, transforms, net, mut prediction): Self::SystemData) {
         let mut net = match net {
             Some(net) => net,
             None => return,
@@ -70,6 +72,7 @@ impl<'a> System<'a> for InputSendSystem {
             if current.actions != self.previous.actions {
                 message = Some(Message::ClientInput {
                     id: 0,
+                    input_sequence: prediction.next_input_sequence(),
                     actions: current.actions.bits(),
                     direction: current.direction,
                 });
@@ -85,6 +88,7 @@ impl<'a> System<'a> for InputSendSystem {
                 if current.time.sub_safely(self.previous.time) > interval {
                     message = Some(Message::ClientInputDirection {
                         id: 0,
+                        input_sequence: prediction.next_input_sequence(),
                         direction: current.direction,
                     });
                 } else {
@@ -5,14 +5,14 @@ use crate::resources::GameTaskResource;
 use crate::resources::Message;
 use crate::resources::NetConnection;
 use crate::resources::NetResource;
+use crate::resources::Packet;
 use crate::resources::PositionUpdateResource;
-use crate::resources::MESSAGE_SIZE_MAX;
+use crate::resources::SnapshotHistory;
 use amethyst::ecs::Entities;
-use amethyst::ecs::Entity;
 use amethyst::ecs::System;
 use amethyst::ecs::Write;
-use std::io::ErrorKind;
 use std::net::SocketAddr;
+use std::time::Instant;
 
 pub struct MessageReceiveSystem;
 
@@ -21,71 +21,151 @@ impl MessageReceiveSystem {
     fn on_message(
         address: &SocketAddr,
         message: &Message,
-        entity: Option<Entity>,
+        connection: &mut NetConnection,
         entities: &Entities,
         converter: &mut EntityConverter,
         tasks: &mut GameTaskResource,
         position_updates: &mut PositionUpdateResource,
+        snapshots: &mut SnapshotHistory,
         is_server: bool,
     ) {
         if is_server {
-            Self::on_message_as_server(address, message, entity, tasks);
+            Self::on_message_as_server(address, message, connection, tasks);
         } else {
-            Self::on_message_as_client(message, entities, converter, tasks, position_updates);
+            Self::on_message_as_client(
+                message,
+                connection,
+                entities,
+                converter,
+                tasks,
+                position_updates,
+                snapshots,
+            );
         }
     }
 
     fn on_message_as_server(
         address: &SocketAddr,
         message: &Message,
-        entity: Option<Entity>,
+        connection: &mut NetConnection,
         tasks: &mut GameTaskResource,
     ) {
+        let entity = connection.attached_entity;
+
         match *message {
-            Message::Join { .. } => {
-                tasks.push(GameTask::ClientJoin(*address));
+            Message::Join {
+                protocol_version,
+                ref build,
+                ..
+            } => {
+                tasks.push(GameTask::ClientJoin {
+                    address: *address,
+                    protocol_version,
+                    build: build.clone(),
+                });
+            }
+            Message::JoinDetails {
+                ref name,
+                is_spectator,
+                session_token,
+                ..
+            } => {
+                tasks.push(GameTask::ClientJoinDetails {
+                    address: *address,
+                    name: name.clone(),
+                    is_spectator,
+                    session_token,
+                });
+            }
+            Message::PlayRequest { .. } => {
+                if connection.is_spectator {
+                    tasks.push(GameTask::PlayRequest(*address));
+                }
             }
             Message::ClientInput {
-                actions, direction, ..
+                input_sequence,
+                actions,
+                direction,
+                ..
             } => {
                 if let Some(entity) = entity {
                     tasks.push(GameTask::ActorAction {
                         entity,
                         actions: ActorActions::from_bits_truncate(actions),
                         direction,
+                        input_sequence,
                     });
                 }
             }
-            Message::ClientInputDirection { direction, .. } => {
+            Message::ClientInputDirection {
+                input_sequence,
+                direction,
+                ..
+            } => {
                 if let Some(entity) = entity {
-                    tasks.push(GameTask::ActorTurn { entity, direction });
+                    tasks.push(GameTask::ActorTurn {
+                        entity,
+                        direction,
+                        input_sequence,
+                    });
                 }
             }
+            Message::SnapshotAck { tick } => {
+                connection.acknowledge_snapshot(tick);
+            }
+            Message::ChatSay { ref text, .. } => {
+                if let Some(sender) = entity {
+                    if connection.try_chat(Instant::now()) {
+                        tasks.push(GameTask::ChatSay {
+                            sender,
+                            text: text.clone(),
+                        });
+                    } else {
+                        log::debug!("Dropped a chat flood message from {}", address);
+                    }
+                }
+            }
+            Message::Leave { ref reason, .. } => {
+                // No need to keep the actor for the client to rejoin
+                connection.session_token = None;
+                connection.disconnect(format!("Left the game: {}", reason));
+            }
             _ => {}
         }
     }
 
     fn on_message_as_client(
         message: &Message,
+        connection: &mut NetConnection,
         entities: &Entities,
         converter: &mut EntityConverter,
         tasks: &mut GameTaskResource,
         position_updates: &mut PositionUpdateResource,
+        snapshots: &mut SnapshotHistory,
     ) {
         match *message {
-            Message::JoinAccept { .. } => {
+            Message::JoinProceed { .. } => {
+                connection.proceed_join();
+            }
+            Message::JoinAccept { session_token, .. } => {
+                connection.session_token = Some(session_token);
                 tasks.push(GameTask::Start);
             }
+            Message::JoinReject { ref reason, .. } => {
+                connection.disconnect(format!("The server has rejected joining: {}", reason));
+            }
             Message::ActorSpawn {
                 entity_id,
                 actor_type,
                 position,
+                health,
                 ..
             } => {
                 tasks.push(GameTask::ActorSpawn {
                     entity: converter.to_internal(entities, entity_id),
                     actor_type: actor_type.into(),
                     position,
+                    health,
                 });
             }
             Message::ActorGrant { entity_id, .. } => {
@@ -93,11 +173,44 @@ impl MessageReceiveSystem {
                     entity: converter.to_internal(entities, entity_id),
                 });
             }
-            Message::PositionUpdate {
-                entity_id,
-                position,
+            Message::ActorDamage {
+                entity_id, health, ..
             } => {
-                position_updates.insert(converter.to_internal(entities, entity_id).id(), position);
+                tasks.push(GameTask::ActorHealth {
+                    entity: converter.to_internal(entities, entity_id),
+                    health,
+                });
+            }
+            Message::ActorDeath { entity_id, .. } => {
+                tasks.push(GameTask::ActorHealth {
+                    entity: converter.to_internal(entities, entity_id),
+                    health: 0.0,
+                });
+            }
+            Message::Snapshot {
+                tick,
+                base_tick,
+                part,
+                parts,
+                entities: ref deltas,
+                ref removed,
+                input,
+            } => {
+                if let Some(changed) =
+                    snapshots.apply(tick, base_tick, part, parts, deltas, removed)
+                {
+                    for (entity_id, position) in changed {
+                        let entity = converter.to_internal(entities, entity_id);
+                        position_updates.positions.insert(entity.id(), position);
+                    }
+
+                    position_updates.input = input;
+
+                    // The server may use the snapshot as a base only once it's complete
+                    if snapshots.get_latest_tick() == Some(tick) {
+                        connection.send(&mut Message::SnapshotAck { tick });
+                    }
+                }
             }
             Message::ProjectileSpawn {
                 position,
@@ -118,6 +231,27 @@ impl MessageReceiveSystem {
                     converter.to_internal(entities, entity_id),
                 ));
             }
+            Message::Roster { ref players, .. } => {
+                tasks.push(GameTask::Roster(players.clone()));
+            }
+            Message::Chat {
+                ref sender,
+                ref text,
+                ..
+            } => {
+                tasks.push(GameTask::Chat {
+                    sender: sender.clone(),
+                    text: text.clone(),
+                });
+            }
+            Message::ServerShutdown { ref reason, .. } => {
+                connection.session_token = None;
+                connection.disconnect(format!("The server has shut down: {}", reason));
+            }
+            Message::Kick { ref reason, .. } => {
+                connection.session_token = None;
+                connection.disconnect(format!("Kicked from the server: {}", reason));
+            }
             _ => {}
         }
     }
@@ -129,12 +263,13 @@ impl<'a> System<'a> for MessageReceiveSystem {
         Write<'a, EntityConverter>,
         Write<'a, GameTaskResource>,
         Write<'a, PositionUpdateResource>,
+        Write<'a, SnapshotHistory>,
         Option<Write<'a, NetResource>>,
     );
 
     fn run(
         &mut self,
-        (entities, mut converter, mut tasks, mut position_updates, net): Self::SystemData,
+        (entities, mut converter, mut tasks, mut position_updates, mut snapshots, net): Self::SystemData,
     ) {
         let mut net = match net {
             Some(net) => net,
@@ -143,83 +278,75 @@ impl<'a> System<'a> for MessageReceiveSystem {
 
         let is_server = net.is_server();
 
-        let mut responses = Vec::new(); // TODO: Find a way send responses without vector allocations
+        for (address, packet) in net.receive() {
+            let is_known = net.connections.contains_key(&address);
+
+            // Clients accept messages from their server only
+            if !is_known && !is_server {
+                continue;
+            }
+
+            match Packet::decode(&packet) {
+                Ok((header, messages)) => {
+                    if !is_known {
+                        // Only joining creates a connection, strangers are ignored otherwise
+                        if !messages.iter().any(|m| matches!(*m, Message::Join { .. })) {
+                            continue;
+                        }
 
-        loop {
-            let mut buffer = [0; MESSAGE_SIZE_MAX];
+                        if !net.try_join(&address) {
+                            log::debug!("Ignored too frequent join attempts from {}", address);
+                            continue;
+                        }
 
-            match net.socket.recv_from(&mut buffer) {
-                Ok((message_length, address)) => {
-                    if !net.connections.contains_key(&address) {
                         log::info!("{} connected", address);
                     }
 
-                    let message = buffer
-                        .get(..message_length)
-                        .ok_or_else(|| "Wrong message length".to_string())
-                        .and_then(|m| Message::decode(m).map_err(|e| format!("{}", e)));
-
-                    match message {
-                        Ok(message) => {
-                            let connection = net
-                                .connections
-                                .entry(address)
-                                .or_insert_with(NetConnection::new);
-
-                            if let Message::Response { message_id } = message {
-                                connection.acknowledge_message(message_id);
-                            } else {
-                                if let Some(message_id) = message.get_id() {
-                                    responses.push((address, Message::Response { message_id }));
-                                }
-
-                                if let Some(message) = connection.filter_message(message) {
-                                    let entity = connection.attached_entity;
-                                    let next_messages = connection.take_next_held_messages();
-
-                                    Self::on_message(
-                                        &address,
-                                        &message,
-                                        entity,
-                                        &entities,
-                                        &mut converter,
-                                        &mut tasks,
-                                        &mut position_updates,
-                                        is_server,
-                                    );
-
-                                    for message in &next_messages {
-                                        Self::on_message(
-                                            &address,
-                                            message,
-                                            entity,
-                                            &entities,
-                                            &mut converter,
-                                            &mut tasks,
-                                            &mut position_updates,
-                                            is_server,
-                                        );
-                                    }
-                                }
+                    let connection = net.get_or_create_connection(address);
+
+                    if !connection.is_connected() {
+                        continue;
+                    }
+
+                    connection.mark_received(packet.len());
+                    connection.acknowledge_messages(header);
+
+                    for message in messages {
+                        if let Some(message) = connection.filter_message(message) {
+                            let next_messages = connection.take_next_held_messages();
+
+                            Self::on_message(
+                                &address,
+                                &message,
+                                connection,
+                                &entities,
+                                &mut converter,
+                                &mut tasks,
+                                &mut position_updates,
+                                &mut snapshots,
+                                is_server,
+                            );
+
+                            for message in &next_messages {
+                                Self::on_message(
+                                    &address,
+                                    message,
+                                    connection,
+                                    &entities,
+                                    &mut converter,
+                                    &mut tasks,
+                                    &mut position_updates,
+                                    &mut snapshots,
+                                    is_server,
+                                );
                             }
                         }
-                        Err(error) => {
-                            log::warn!("A corrupted message received from {}: {}", address, error);
-                        }
                     }
                 }
                 Err(error) => {
-                    if error.kind() == ErrorKind::WouldBlock {
-                        break;
-                    } else {
-                        log::error!("Failed to receive new messages. {}", error);
-                    }
+                    log::warn!("A corrupted packet received from {}: {}", address, error);
                 }
             }
         }
-
-        for (address, message) in responses {
-            net.send_to(&address, message);
-        }
     }
 }
@@ -1,13 +1,25 @@
 mod connection_update;
+mod discovery;
 mod input_send;
 mod interpolation;
 mod message_receive;
+mod net_stats;
+mod position_history;
 mod position_update;
-mod position_update_send;
+mod prediction;
+mod relevance;
+mod roster;
+mod snapshot_send;
 
 pub use self::connection_update::*;
+pub use self::discovery::*;
 pub use self::input_send::*;
 pub use self::interpolation::*;
 pub use self::message_receive::*;
+pub use self::net_stats::*;
+pub use self::position_history::*;
 pub use self::position_update::*;
-pub use self::position_update_send::*;
+pub use self::prediction::*;
+pub use self::relevance::*;
+pub use self::roster::*;
+pub use self::snapshot_send::*;
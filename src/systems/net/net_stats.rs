@@ -0,0 +1,261 @@
+use crate::models::NetStatsDump;
+use crate::resources::NetMetrics;
+use crate::resources::NetResource;
+use amethyst::core::timing::Time;
+use amethyst::ecs::Join;
+use amethyst::ecs::Read;
+use amethyst::ecs::ReadStorage;
+use amethyst::ecs::System;
+use amethyst::ecs::Write;
+use amethyst::ecs::WriteStorage;
+use amethyst::ui::UiText;
+use amethyst::ui::UiTransform;
+use std::collections::HashMap;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Write as IoWrite;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+
+const OVERLAY_TEXT_ID: &str = "net_stats.text";
+
+const CSV_HEADER: &str = "time,address,rtt_ms,packets_sent,packets_received,bytes_sent,\
+    bytes_received,resent,unacknowledged,held,discarded,deferred";
+
+/// Samples statistics of every connection, shows them on the overlay and dumps them if configured
+pub struct NetStatsSystem {
+    last_sample: Option<Duration>,
+    previous: HashMap<SocketAddr, NetMetrics>,
+    csv: Option<File>,
+    is_csv_failed: bool,
+}
+
+impl NetStatsSystem {
+    pub fn new() -> Self {
+        return Self {
+            last_sample: None,
+            previous: HashMap::new(),
+            csv: None,
+            is_csv_failed: false,
+        };
+    }
+
+    fn dump(&mut self, dump: &NetStatsDump, now: Duration, samples: &[(SocketAddr, NetMetrics)]) {
+        match *dump {
+            NetStatsDump::Log => {
+                for (address, metrics) in samples {
+                    log::info!("{}: {}", address, format_summary(metrics));
+                }
+            }
+            NetStatsDump::Csv(ref path) => {
+                if self.is_csv_failed {
+                    return;
+                }
+
+                if let Err(error) = self.write_csv(path, now, samples) {
+                    log::error!("Failed to dump network stats to {:?}: {}", path, error);
+                    self.is_csv_failed = true;
+                }
+            }
+        }
+    }
+
+    fn write_csv(
+        &mut self,
+        path: &Path,
+        now: Duration,
+        samples: &[(SocketAddr, NetMetrics)],
+    ) -> std::io::Result<()> {
+        if self.csv.is_none() {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+            if file.metadata()?.len() == 0 {
+                writeln!(file, "{}", CSV_HEADER)?;
+            }
+
+            self.csv = Some(file);
+        }
+
+        if let Some(file) = self.csv.as_mut() {
+            for (address, metrics) in samples {
+                writeln!(file, "{}", format_csv_row(now, address, metrics))?;
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+impl<'a> System<'a> for NetStatsSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        Option<Write<'a, NetResource>>,
+        ReadStorage<'a, UiTransform>,
+        WriteStorage<'a, UiText>,
+    );
+
+    fn run(&mut self, (time, net, transforms, mut texts): Self::SystemData) {
+        let net = match net {
+            Some(net) => net,
+            None => return,
+        };
+
+        let now = time.absolute_real_time();
+        let interval = net.get_config().stats_interval;
+
+        if let Some(last_sample) = self.last_sample {
+            if now < last_sample + interval {
+                return;
+            }
+        }
+
+        let elapsed = self.last_sample.map(|t| now - t);
+        self.last_sample = Some(now);
+
+        let mut samples = net
+            .connections
+            .iter()
+            .map(|(address, connection)| (*address, connection.get_metrics()))
+            .collect::<Vec<(SocketAddr, NetMetrics)>>();
+
+        samples.sort_by_key(|(address, _)| *address);
+
+        let mut overlay = String::new();
+
+        if samples.is_empty() {
+            overlay.push_str("No connections");
+        }
+
+        for (address, metrics) in &samples {
+            let previous = self.previous.get(address).copied().unwrap_or_default();
+            overlay.push_str(&format!(
+                "{}\n{}\n{}\n\n",
+                address,
+                format_summary(metrics),
+                format_rates(metrics, &previous, elapsed.unwrap_or_default()),
+            ));
+        }
+
+        for (transform, text) in (&transforms, &mut texts).join() {
+            if transform.id == OVERLAY_TEXT_ID {
+                text.text = overlay;
+                break;
+            }
+        }
+
+        if let Some(dump) = net.get_config().stats_dump.as_ref() {
+            self.dump(dump, now, &samples);
+        }
+
+        self.previous = samples.into_iter().collect();
+    }
+}
+
+fn format_summary(metrics: &NetMetrics) -> String {
+    return format!(
+        "rtt {}, sent {} ({} B), received {} ({} B), resent {}, unacknowledged {}, held {}, \
+        discarded {}, deferred {}",
+        metrics
+            .rtt
+            .map_or_else(|| "?".to_string(), |rtt| format!("{} ms", rtt.as_millis())),
+        metrics.packets_sent,
+        metrics.bytes_sent,
+        metrics.packets_received,
+        metrics.bytes_received,
+        metrics.resent,
+        metrics.unacknowledged,
+        metrics.held,
+        metrics.discarded,
+        metrics.deferred,
+    );
+}
+
+fn format_rates(metrics: &NetMetrics, previous: &NetMetrics, elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs_f64();
+
+    let rate = |current: u64, previous: u64| {
+        if seconds > 0.0 {
+            current.saturating_sub(previous) as f64 / seconds
+        } else {
+            0.0
+        }
+    };
+
+    return format!(
+        "out {:.0} pkt/s {:.1} KB/s, in {:.0} pkt/s {:.1} KB/s",
+        rate(metrics.packets_sent, previous.packets_sent),
+        rate(metrics.bytes_sent, previous.bytes_sent) / 1024.0,
+        rate(metrics.packets_received, previous.packets_received),
+        rate(metrics.bytes_received, previous.bytes_received) / 1024.0,
+    );
+}
+
+fn format_csv_row(now: Duration, address: &SocketAddr, metrics: &NetMetrics) -> String {
+    return format!(
+        "{:.3},{},{},{},{},{},{},{},{},{},{},{}",
+        now.as_secs_f64(),
+        address,
+        metrics
+            .rtt
+            .map_or_else(String::new, |rtt| rtt.as_millis().to_string()),
+        metrics.packets_sent,
+        metrics.packets_received,
+        metrics.bytes_sent,
+        metrics.bytes_received,
+        metrics.resent,
+        metrics.unacknowledged,
+        metrics.held,
+        metrics.discarded,
+        metrics.deferred,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_csv_row() -> Result<(), std::net::AddrParseError> {
+        let metrics = NetMetrics {
+            packets_sent: 3,
+            bytes_sent: 120,
+            ..NetMetrics::default()
+        };
+
+        let row = format_csv_row(
+            Duration::from_millis(1500),
+            &"127.0.0.1:2003".parse()?,
+            &metrics,
+        );
+
+        assert_eq!("1.500,127.0.0.1:2003,,3,0,120,0,0,0,0,0,0", row);
+        assert_eq!(
+            CSV_HEADER.split(',').count(),
+            row.split(',').count(),
+            "Every column is filled",
+        );
+
+        return Ok(());
+    }
+
+    #[test]
+    fn test_format_rates() {
+        let previous = NetMetrics {
+            packets_received: 10,
+            bytes_received: 1024,
+            ..NetMetrics::default()
+        };
+
+        let metrics = NetMetrics {
+            packets_received: 30,
+            bytes_received: 5120,
+            ..previous
+        };
+
+        assert_eq!(
+            "out 0 pkt/s 0.0 KB/s, in 10 pkt/s 2.0 KB/s",
+            format_rates(&metrics, &previous, Duration::from_secs(2)),
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use crate::components::Collision;
+use crate::resources::NetResource;
+use crate::resources::PositionHistory;
+use amethyst::core::timing::Time;
+use amethyst::core::transform::Transform;
+use amethyst::ecs::Entities;
+use amethyst::ecs::Join;
+use amethyst::ecs::Read;
+use amethyst::ecs::ReadStorage;
+use amethyst::ecs::System;
+use amethyst::ecs::Write;
+
+/// Records positions of collidable entities every tick for lag compensation
+pub struct PositionHistorySystem;
+
+impl<'a> System<'a> for PositionHistorySystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        Option<Read<'a, NetResource>>,
+        ReadStorage<'a, Collision>,
+        ReadStorage<'a, Transform>,
+        Write<'a, PositionHistory>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, time, net, collisions, transforms, mut history): Self::SystemData,
+    ) {
+        let net = match net {
+            Some(net) => net,
+            None => return,
+        };
+
+        let positions = (&entities, &collisions, &transforms)
+            .join()
+            .map(|(entity, _, transform)| (entity.id(), transform.translation().xy()))
+            .collect();
+
+        history.push(time.absolute_time(), positions, net.get_config().rewind_max);
+    }
+}
@@ -1,6 +1,11 @@
+use crate::components::Actor;
+use crate::components::Collision;
 use crate::components::Interpolation;
 use crate::components::Player;
+use crate::components::RigidBody;
 use crate::resources::PositionUpdateResource;
+use crate::resources::PredictionResource;
+use crate::systems::net;
 use crate::utils;
 use crate::utils::Position;
 use amethyst::core::timing::Time;
@@ -15,6 +20,7 @@ use amethyst::ecs::Write;
 use amethyst::ecs::WriteStorage;
 
 const MAX_PLAYER_OFFSET: f32 = 0.25;
+const MAX_PLAYER_OFFSET_PREDICTED: f32 = 0.03;
 
 pub struct PositionUpdateSystem;
 
@@ -22,28 +28,46 @@ impl<'a> System<'a> for PositionUpdateSystem {
     type SystemData = (
         Entities<'a>,
         Read<'a, Time>,
+        ReadStorage<'a, Actor>,
+        ReadStorage<'a, Collision>,
         ReadStorage<'a, Player>,
+        ReadStorage<'a, RigidBody>,
         ReadStorage<'a, Transform>,
         Write<'a, PositionUpdateResource>,
+        Write<'a, PredictionResource>,
         WriteStorage<'a, Interpolation>,
     );
 
     fn run(
         &mut self,
-        (entities, time, players, transforms, mut updates, mut interpolations): Self::SystemData,
+        (
+            entities,
+            time,
+            actors,
+            collisions,
+            players,
+            bodies,
+            transforms,
+            mut updates,
+            mut prediction,
+            mut interpolations,
+        ): Self::SystemData,
     ) {
-        let mut ghost_update: Option<(Entity, &Position)> = None;
+        let mut ghost_update: Option<(Entity, Position)> = None;
         let now = time.absolute_time();
+        let input = updates.input;
 
-        for (entity, transform, interpolation, player) in (
+        for (entity, transform, interpolation, player, actor, body) in (
             &entities,
             &transforms,
             &mut interpolations,
             (&players).maybe(),
+            (&actors).maybe(),
+            (&bodies).maybe(),
         )
             .join()
         {
-            let update = updates.get(&entity.id()).or_else(|| {
+            let update = updates.positions.get(&entity.id()).copied().or_else(|| {
                 if let Some((ghost, update)) = ghost_update {
                     if ghost == entity {
                         return Some(update);
@@ -54,10 +78,34 @@ impl<'a> System<'a> for PositionUpdateSystem {
             });
 
             if let Some(update) = update {
+                let mut target = update;
                 let fix_position;
 
                 if let Some(player) = player {
-                    fix_position = is_offset_noticeable(transform, update);
+                    if let (Some(input), Some(actor), Some(body), Some(collision)) =
+                        (input, actor, body, collisions.get(entity))
+                    {
+                        let obstacles = (&entities, &transforms, &collisions, (&bodies).maybe())
+                            .join()
+                            .filter(|(e, ..)| *e != entity)
+                            .map(|(_, t, c, b)| (t, c, b))
+                            .collect::<Vec<_>>();
+
+                        target = net::replay(
+                            &mut prediction,
+                            input,
+                            update,
+                            actor.actor_type,
+                            collision,
+                            body,
+                            &obstacles,
+                        );
+                        target.direction = transform.euler_angles().2;
+                        fix_position =
+                            is_offset_noticeable(transform, &target, MAX_PLAYER_OFFSET_PREDICTED);
+                    } else {
+                        fix_position = is_offset_noticeable(transform, &update, MAX_PLAYER_OFFSET);
+                    }
 
                     if let Some(ghost) = player.ghost {
                         ghost_update.replace((ghost, update));
@@ -67,7 +115,7 @@ impl<'a> System<'a> for PositionUpdateSystem {
                 }
 
                 if fix_position {
-                    interpolation.next(*update, now);
+                    interpolation.next(target, now);
                 }
             }
         }
@@ -76,8 +124,8 @@ impl<'a> System<'a> for PositionUpdateSystem {
     }
 }
 
-fn is_offset_noticeable(transform: &Transform, update: &Position) -> bool {
+fn is_offset_noticeable(transform: &Transform, update: &Position, max_offset: f32) -> bool {
     let offset_x = update.x - transform.translation().x;
     let offset_y = update.y - transform.translation().y;
-    return !utils::math::are_closer_than(offset_x, offset_y, 0.0, 0.0, MAX_PLAYER_OFFSET);
+    return !utils::math::are_closer_than(offset_x, offset_y, 0.0, 0.0, max_offset);
 }
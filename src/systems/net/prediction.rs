@@ -0,0 +1,133 @@
+use crate::components::Actor;
+use crate::components::ActorType;
+use crate::components::Collision;
+use crate::components::Player;
+use crate::components::RigidBody;
+use crate::resources::InputEcho;
+use crate::resources::PredictionResource;
+use crate::systems;
+use crate::utils::Position;
+use amethyst::core::timing::Time;
+use amethyst::core::transform::Transform;
+use amethyst::ecs::Join;
+use amethyst::ecs::Read;
+use amethyst::ecs::ReadStorage;
+use amethyst::ecs::System;
+use amethyst::ecs::Write;
+
+/// Records every simulated frame of the player so it can be replayed on top of server positions
+pub struct PredictionSystem;
+
+impl<'a> System<'a> for PredictionSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        ReadStorage<'a, Actor>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, RigidBody>,
+        ReadStorage<'a, Transform>,
+        Write<'a, PredictionResource>,
+    );
+
+    fn run(
+        &mut self,
+        (time, actors, players, bodies, transforms, mut prediction): Self::SystemData,
+    ) {
+        #[allow(clippy::never_loop)]
+        for (_, actor, body, transform) in (&players, &actors, &bodies, &transforms).join() {
+            prediction.push(
+                actor.actions,
+                transform.euler_angles().2,
+                time.delta_seconds(),
+                body,
+            );
+
+            break; // since there should be only one player
+        }
+    }
+}
+
+/// Replays the player's frames the server hasn't simulated yet on top of the server position
+/// and returns where the player is supposed to be now. Every frame is simulated the way actor and
+/// physics systems do, colliding with obstacles where they are now
+#[allow(clippy::too_many_arguments)]
+pub fn replay(
+    prediction: &mut PredictionResource,
+    echo: InputEcho,
+    position: Position,
+    actor_type: &ActorType,
+    collision: &Collision,
+    body: &RigidBody,
+    obstacles: &[(&Transform, &Collision, Option<&RigidBody>)],
+) -> Position {
+    let (confirmed, frames) = prediction.confirm(echo);
+    let mut transform = Transform::default();
+
+    // The current body is ahead of the server, so start from a still one if nothing is confirmed
+    let mut body = confirmed
+        .unwrap_or_else(|| RigidBody::new(body.mass, body.drag, body.drag_angular, body.rigidity));
+
+    transform.set_translation_xyz(position.x, position.y, 0.0);
+
+    for frame in frames {
+        transform.set_rotation_2d(frame.direction);
+        systems::move_actor(
+            frame.actions,
+            actor_type,
+            &transform,
+            &mut body,
+            frame.delta,
+        );
+        systems::collide(&mut transform, collision, &mut body, obstacles, frame.delta);
+        systems::integrate(&mut transform, &mut body, frame.delta);
+    }
+
+    return Position::from(&transform);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::ActorActions;
+
+    fn replay_forward(obstacles: &[(&Transform, &Collision, Option<&RigidBody>)]) -> f32 {
+        let actor_type = ActorType::HUMAN;
+        let body = RigidBody::new(actor_type.mass, 7.0, 8.0, 0.05);
+        let collision = Collision {
+            radius: actor_type.radius,
+        };
+
+        let mut prediction = PredictionResource::new();
+
+        for _ in 0..120 {
+            prediction.push(ActorActions::MOVEMENT_FORWARD, 0.0, 1.0 / 60.0, &body);
+        }
+
+        let position = replay(
+            &mut prediction,
+            InputEcho {
+                sequence: 0,
+                age: 0,
+            },
+            Position::new(0.0, 0.0, 0.0),
+            actor_type,
+            &collision,
+            &body,
+            obstacles,
+        );
+
+        return position.y;
+    }
+
+    #[test]
+    fn test_replay_collision() {
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 1.0, 0.0);
+        let collision = Collision { radius: 0.25 };
+
+        assert!(replay_forward(&[]) > 2.0, "Free way");
+        assert!(
+            replay_forward(&[(&transform, &collision, None)]) < 0.75,
+            "Stopped by the obstacle",
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use crate::components::Actor;
+use crate::components::Health;
+use crate::data::VIEW_DISTANCE;
+use crate::resources::Message;
+use crate::resources::NetConnection;
+use crate::resources::NetResource;
+use crate::utils;
+use amethyst::core::math::Vector2;
+use amethyst::core::transform::Transform;
+use amethyst::ecs::Entities;
+use amethyst::ecs::Join;
+use amethyst::ecs::ReadStorage;
+use amethyst::ecs::System;
+use amethyst::ecs::Write;
+use std::collections::HashSet;
+
+/// Keeps an entity spawned a bit beyond the view distance, so it doesn't blink at the border
+const DESPAWN_MARGIN: f32 = 2.0;
+
+/// Spawns actors on clients when they come close to client's own actor and despawns them when
+/// they leave. Grants the own actor once it's spawned. Spectators get every actor
+pub struct RelevanceSystem;
+
+/// What a client sees
+pub enum View {
+    /// Spectators see the whole world
+    Everything,
+    /// Players see around their actors
+    Around(Vector2<f32>),
+}
+
+impl View {
+    /// Players see nothing until their actors are spawned
+    pub fn of(connection: &NetConnection, transforms: &ReadStorage<Transform>) -> Option<Self> {
+        if connection.is_spectator {
+            return Some(Self::Everything);
+        } else {
+            return connection
+                .attached_entity
+                .and_then(|e| transforms.get(e))
+                .map(|t| Self::Around(t.translation().xy()));
+        }
+    }
+
+    pub fn contains(&self, x: f32, y: f32, distance: f32) -> bool {
+        return match *self {
+            Self::Everything => true,
+            Self::Around(center) => {
+                utils::math::are_closer_than(x, y, center.x, center.y, distance)
+            }
+        };
+    }
+}
+
+impl<'a> System<'a> for RelevanceSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Actor>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Transform>,
+        Option<Write<'a, NetResource>>,
+    );
+
+    fn run(&mut self, (entities, actors, healths, transforms, net): Self::SystemData) {
+        let mut net = match net {
+            Some(net) => net,
+            None => return,
+        };
+
+        for connection in net.connections.values_mut() {
+            let view = match View::of(connection, &transforms) {
+                Some(view) => view,
+                None => continue,
+            };
+
+            let own = connection
+                .attached_entity
+                .filter(|_| !connection.is_spectator);
+
+            let mut relevant = HashSet::new();
+
+            for (entity, _, transform) in (&entities, &actors, &transforms).join() {
+                let distance;
+
+                if connection.is_relevant(entity) {
+                    distance = VIEW_DISTANCE + DESPAWN_MARGIN;
+                } else {
+                    distance = VIEW_DISTANCE;
+                }
+
+                let position = transform.translation();
+
+                if Some(entity) == own || view.contains(position.x, position.y, distance) {
+                    relevant.insert(entity);
+                }
+            }
+
+            let previous = connection.replace_relevant_entities(relevant.clone());
+
+            for entity in previous.difference(&relevant) {
+                connection.send(&mut Message::EntityDelete {
+                    id: 0,
+                    entity_id: entity.id(),
+                });
+            }
+
+            for entity in relevant.difference(&previous) {
+                if let (Some(actor), Some(transform)) =
+                    (actors.get(*entity), transforms.get(*entity))
+                {
+                    connection.send(&mut Message::ActorSpawn {
+                        id: 0,
+                        entity_id: entity.id(),
+                        actor_type: actor.actor_type.serialized,
+                        position: transform.into(),
+                        health: healths.get(*entity).map_or(1.0, Health::get),
+                    });
+                }
+
+                if Some(*entity) == own {
+                    connection.send(&mut Message::ActorGrant {
+                        id: 0,
+                        entity_id: entity.id(),
+                    });
+                }
+            }
+        }
+    }
+}
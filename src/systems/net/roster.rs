@@ -0,0 +1,56 @@
+use crate::resources::Message;
+use crate::resources::NetResource;
+use crate::resources::RosterResource;
+use crate::utils::Timer;
+use amethyst::core::timing::Time;
+use amethyst::ecs::Read;
+use amethyst::ecs::System;
+use amethyst::ecs::Write;
+use std::time::Duration;
+
+const PING_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Keeps pings of players up to date and tells clients about every change of the roster
+pub struct RosterSystem {
+    timer: Timer,
+}
+
+impl RosterSystem {
+    pub const fn new() -> Self {
+        return Self {
+            timer: Timer::new(PING_UPDATE_INTERVAL),
+        };
+    }
+}
+
+impl<'a> System<'a> for RosterSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        Write<'a, RosterResource>,
+        Option<Write<'a, NetResource>>,
+    );
+
+    fn run(&mut self, (time, mut roster, net): Self::SystemData) {
+        let mut net = match net {
+            Some(net) => net,
+            None => return,
+        };
+
+        if self.timer.next_if_done(time.absolute_real_time()) {
+            for connection in net.connections.values() {
+                if let (Some(entity), Some(rtt)) =
+                    (connection.attached_entity, connection.get_rtt())
+                {
+                    roster.set_ping(entity, rtt);
+                }
+            }
+        }
+
+        if roster.take_changed() {
+            net.send_to_all(Message::Roster {
+                id: 0,
+                players: roster.get_players().to_vec(),
+            });
+        }
+    }
+}
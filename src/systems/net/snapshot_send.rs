@@ -0,0 +1,95 @@
+use crate::components::Actor;
+use crate::data::POSITION_UPDATE_INTERVAL;
+use crate::resources::NetResource;
+use crate::resources::Snapshot;
+use crate::resources::SnapshotHistory;
+use crate::utils::Position;
+use crate::utils::Timer;
+use amethyst::core::timing::Time;
+use amethyst::core::transform::Transform;
+use amethyst::ecs::Entities;
+use amethyst::ecs::Entity;
+use amethyst::ecs::Join;
+use amethyst::ecs::Read;
+use amethyst::ecs::ReadStorage;
+use amethyst::ecs::System;
+use amethyst::ecs::Write;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+pub struct SnapshotSendSystem {
+    timer: Timer,
+    tick: u16,
+    histories: HashMap<SocketAddr, SnapshotHistory>,
+}
+
+impl SnapshotSendSystem {
+    pub fn new() -> Self {
+        return Self {
+            timer: Timer::new(POSITION_UPDATE_INTERVAL),
+            tick: 0,
+            histories: HashMap::new(),
+        };
+    }
+}
+
+impl<'a> System<'a> for SnapshotSendSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        Option<Write<'a, NetResource>>,
+        ReadStorage<'a, Actor>,
+        ReadStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, (entities, time, net, actors, transforms): Self::SystemData) {
+        if !self.timer.next_if_done(time.absolute_real_time()) {
+            return;
+        }
+
+        let mut net = match net {
+            Some(net) => net,
+            None => return,
+        };
+
+        self.tick = self.tick.wrapping_add(1);
+
+        let positions: Vec<(Entity, Position)> = (&entities, &actors, &transforms)
+            .join()
+            .map(|(entity, _, transform)| (entity, Position::from(transform)))
+            .collect();
+
+        self.histories
+            .retain(|address, _| net.connections.contains_key(address));
+
+        for (address, connection) in &mut net.connections {
+            let mut snapshot = Snapshot::new();
+
+            for (entity, position) in &positions {
+                if connection.is_relevant(*entity) {
+                    snapshot.insert(entity.id(), *position);
+                }
+            }
+
+            let history = self
+                .histories
+                .entry(*address)
+                .or_insert_with(SnapshotHistory::new);
+
+            let base = connection
+                .get_acknowledged_snapshot()
+                .and_then(|tick| history.get(tick).map(|s| (tick, s)));
+
+            let own = connection
+                .attached_entity
+                .map(Entity::id)
+                .zip(connection.get_input_echo());
+
+            for mut message in snapshot.to_messages(self.tick, base, own) {
+                connection.send(&mut message);
+            }
+
+            history.push(self.tick, snapshot);
+        }
+    }
+}
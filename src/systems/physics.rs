@@ -56,29 +56,14 @@ impl<'a> System<'a> for PhysicsSystem {
             (&e, &t, &c, (&b).maybe(), &o).join().collect();
 
         for (e1, t1, c1, b1) in (&e, &t, &c, (&b).maybe()).join() {
-            let p1 = predict_position(t1, b1, delta);
-
             for (e2, t2, c2, b2, _own) in &sub_query {
                 if e1.id() == e2.id() || e2.id() <= last_checked_entity_id {
                     continue;
                 }
 
-                let p2 = predict_position(t2, *b2, delta);
-
-                if let Some(shift) = Collision::resolve(c1, c2, p1, p2) {
-                    let mut push_1 = Vector2::new(0.0, 0.0);
-                    let mut push_2 = Vector2::new(0.0, 0.0);
-
-                    if let (Some(b1), Some(b2)) = (b1, b2) {
-                        // TODO: Maybe collision solutions would contain relative_angle
-                        let relative_angle = (t2.translation() - t1.translation()).xy().normalize();
-                        let push = RigidBody::bounce(b1, b2, relative_angle);
-                        push_1 += &push;
-                        push_2 -= &push;
-                    }
-
-                    append_solution(&mut solutions, e1.id(), shift, push_1);
-                    append_solution(&mut solutions, e2.id(), -shift, push_2);
+                if let Some((shift, push)) = resolve_collision((t1, c1, b1), (t2, c2, *b2), delta) {
+                    append_solution(&mut solutions, e1.id(), shift, push);
+                    append_solution(&mut solutions, e2.id(), -shift, -push);
                     self.previous_collisions_count += 1;
                 }
             }
@@ -124,14 +109,7 @@ impl<'a> System<'a> for PhysicsSystem {
             }
 
             if let (Some(body), Some(..)) = (body.as_mut(), own) {
-                let translation = transform.translation_mut();
-                translation.x += body.velocity.x * delta;
-                translation.y += body.velocity.y * delta;
-
-                transform.rotate_2d(body.spinning * delta);
-
-                body.velocity *= 1.0 - delta * body.drag;
-                body.spinning *= 1.0 - delta * body.drag_angular;
+                integrate(transform, body, delta);
             }
 
             if let Some(interpolation) = interpolation {
@@ -142,6 +120,69 @@ impl<'a> System<'a> for PhysicsSystem {
     }
 }
 
+/// Returns how far to shift the first entity out of the second one and the impulse to push its
+/// body with, if they collide by the next frame
+pub fn resolve_collision(
+    (t1, c1, b1): (&Transform, &Collision, Option<&RigidBody>),
+    (t2, c2, b2): (&Transform, &Collision, Option<&RigidBody>),
+    delta: f32,
+) -> Option<(Vector2<f32>, Vector2<f32>)> {
+    let p1 = predict_position(t1, b1, delta);
+    let p2 = predict_position(t2, b2, delta);
+    let shift = Collision::resolve(c1, c2, p1, p2)?;
+    let mut push = Vector2::new(0.0, 0.0);
+
+    if let (Some(b1), Some(b2)) = (b1, b2) {
+        // TODO: Maybe collision solutions would contain relative_angle
+        let relative_angle = (t2.translation() - t1.translation()).xy().normalize();
+        push = RigidBody::bounce(b1, b2, relative_angle);
+    }
+
+    return Some((shift, push));
+}
+
+/// Resolves collisions of a single body the way the system does, but leaves obstacles in place.
+/// Lets the client replay its player's movement
+pub fn collide(
+    transform: &mut Transform,
+    collision: &Collision,
+    body: &mut RigidBody,
+    obstacles: &[(&Transform, &Collision, Option<&RigidBody>)],
+    delta: f32,
+) {
+    let mut shift = Vector2::new(0.0, 0.0);
+    let mut push = Vector2::new(0.0, 0.0);
+
+    for (t2, c2, b2) in obstacles {
+        if let Some((s, p)) = resolve_collision(
+            (&*transform, collision, Some(&*body)),
+            (*t2, *c2, *b2),
+            delta,
+        ) {
+            shift += s;
+            push += p;
+        }
+    }
+
+    let translation = transform.translation_mut();
+    translation.x += shift.x;
+    translation.y += shift.y;
+
+    body.push(push.x, push.y, 0.0, true, false);
+}
+
+/// Moves the transform by the body velocity and slows the body down by its drag
+pub fn integrate(transform: &mut Transform, body: &mut RigidBody, delta: f32) {
+    let translation = transform.translation_mut();
+    translation.x += body.velocity.x * delta;
+    translation.y += body.velocity.y * delta;
+
+    transform.rotate_2d(body.spinning * delta);
+
+    body.velocity *= 1.0 - delta * body.drag;
+    body.spinning *= 1.0 - delta * body.drag_angular;
+}
+
 fn predict_position(transform: &Transform, body: Option<&RigidBody>, delta: f32) -> Vector2<f32> {
     let mut predicted = transform.translation().xy();
 
@@ -4,6 +4,7 @@ use crate::components::Player;
 use crate::input::ActionBinding;
 use crate::input::AxisBinding;
 use crate::input::CustomBindingTypes;
+use crate::resources::ChatResource;
 use crate::resources::MouseInput;
 use amethyst::ecs::Join;
 use amethyst::ecs::Read;
@@ -21,17 +22,24 @@ pub struct PlayerSystem;
 impl<'a> System<'a> for PlayerSystem {
     type SystemData = (
         Read<'a, InputHandler<CustomBindingTypes>>,
+        Read<'a, ChatResource>,
         ReadStorage<'a, Player>,
         Write<'a, MouseInput>,
         WriteStorage<'a, Actor>,
     );
 
-    fn run(&mut self, (input, players, mut input_mouse, mut actors): Self::SystemData) {
+    fn run(&mut self, (input, chat, players, mut input_mouse, mut actors): Self::SystemData) {
         let rotation = (input_mouse.delta_x * ROTATION_SENSITIVITY) % TAU;
 
         for (actor, _) in (&mut actors, &players).join() {
             actor.rotation = rotation;
 
+            // Keys are typed into the chat
+            if chat.is_typing() {
+                actor.actions = ActorActions::empty();
+                continue;
+            }
+
             apply_movement_input(
                 &mut actor.actions,
                 ActorActions::MOVEMENT_FORWARD,
@@ -4,6 +4,7 @@ use crate::components::Projectile;
 use crate::data::LAYER_PROJECTILE;
 use crate::resources::GameTask;
 use crate::resources::GameTaskResource;
+use crate::resources::PositionHistory;
 use crate::utils::math;
 use crate::utils::DurationExt;
 use amethyst::core::math::Point3;
@@ -32,12 +33,13 @@ impl<'a> System<'a> for ProjectileSystem {
     type SystemData = (
         Entities<'a>,
         Read<'a, Time>,
+        Read<'a, PositionHistory>,
         ReadStorage<'a, Own>,
         ReadStorage<'a, Projectile>,
         ReadStorage<'a, Collision>,
         ReadStorage<'a, Transform>,
         Write<'a, GameTaskResource>,
-        Write<'a, DebugLines>,
+        Option<Write<'a, DebugLines>>,
     );
 
     fn run(
@@ -45,6 +47,7 @@ impl<'a> System<'a> for ProjectileSystem {
         (
             entities,
             time,
+            history,
             own,
             projectiles,
             collisions,
@@ -60,6 +63,7 @@ impl<'a> System<'a> for ProjectileSystem {
         for (entity, projectile) in (&entities, &projectiles).join() {
             let (mut head_position, head_velocity) = projectile.calc_data(time_current);
             let (tail_position, _) = projectile.calc_data(time_previous);
+            let time_rewound = time_current.sub_safely(projectile.rewind);
             let mut obstacle: Option<Obstacle> = None;
             let sub_query = sub_query.get_or_insert_with(|| {
                 return (&entities, &collisions, &transforms, (&own).maybe())
@@ -72,7 +76,9 @@ impl<'a> System<'a> for ProjectileSystem {
                     continue;
                 }
 
-                let obstacle_position = transform.translation().xy();
+                let obstacle_position = history
+                    .get(entity.id(), time_rewound)
+                    .unwrap_or_else(|| transform.translation().xy());
 
                 if is_collision(
                     head_position,
@@ -118,15 +124,18 @@ impl<'a> System<'a> for ProjectileSystem {
                         entity: obstacle.entity,
                         force_x: head_velocity.x * Projectile::MASS,
                         force_y: head_velocity.y * Projectile::MASS,
+                        shooter: projectile.shooter,
                     });
                 }
             }
 
-            debug.draw_line(
-                Point3::from([head_position.x, head_position.y, LAYER_PROJECTILE]),
-                Point3::from([tail_position.x, tail_position.y, LAYER_PROJECTILE]),
-                Srgba::new(1.0, 1.0, 0.0, 1.0),
-            );
+            if let Some(debug) = debug.as_mut() {
+                debug.draw_line(
+                    Point3::from([head_position.x, head_position.y, LAYER_PROJECTILE]),
+                    Point3::from([tail_position.x, tail_position.y, LAYER_PROJECTILE]),
+                    Srgba::new(1.0, 1.0, 0.0, 1.0),
+                );
+            }
 
             if obstacle.is_some() || has_stopped(head_velocity) {
                 if let Err(error) = entities.delete(entity) {
@@ -0,0 +1,78 @@
+use crate::components::Spectator;
+use crate::input::AxisBinding;
+use crate::input::CustomBindingTypes;
+use crate::resources::ChatResource;
+use crate::resources::MouseInput;
+use amethyst::core::timing::Time;
+use amethyst::core::transform::Transform;
+use amethyst::ecs::Entities;
+use amethyst::ecs::Join;
+use amethyst::ecs::Read;
+use amethyst::ecs::ReadStorage;
+use amethyst::ecs::System;
+use amethyst::ecs::WriteStorage;
+use amethyst::input::InputHandler;
+
+const ROTATION_SENSITIVITY: f32 = 0.003;
+
+/// Meters per second
+const MOVEMENT_VELOCITY: f32 = 8.0;
+
+/// Moves spectators with the same controls as players, or keeps them on their targets
+pub struct SpectatorSystem;
+
+impl<'a> System<'a> for SpectatorSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        Read<'a, InputHandler<CustomBindingTypes>>,
+        Read<'a, ChatResource>,
+        Read<'a, MouseInput>,
+        ReadStorage<'a, Spectator>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, time, input, chat, mouse_input, spectators, mut transforms): Self::SystemData,
+    ) {
+        let spectators = (&entities, &spectators)
+            .join()
+            .map(|(entity, spectator)| (entity, spectator.target))
+            .collect::<Vec<_>>();
+
+        for (entity, target) in spectators {
+            if let Some(target) = target.and_then(|t| transforms.get(t)).cloned() {
+                if let Some(transform) = transforms.get_mut(entity) {
+                    let z = transform.translation().z;
+                    *transform = target;
+                    transform.set_translation_z(z);
+                }
+
+                continue;
+            }
+
+            if let Some(transform) = transforms.get_mut(entity) {
+                transform.rotate_2d(mouse_input.delta_x * ROTATION_SENSITIVITY);
+
+                // Keys are typed into the chat
+                if chat.is_typing() {
+                    continue;
+                }
+
+                let distance = MOVEMENT_VELOCITY * time.delta_real_seconds();
+
+                transform.move_up(
+                    input
+                        .axis_value(&AxisBinding::MovementForward)
+                        .unwrap_or(0.0)
+                        * distance,
+                );
+
+                transform.move_right(
+                    input.axis_value(&AxisBinding::MovementAside).unwrap_or(0.0) * distance,
+                );
+            }
+        }
+    }
+}
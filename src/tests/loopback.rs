@@ -0,0 +1,379 @@
+use crate::components::Actor;
+use crate::models::ConsoleCommand;
+use crate::models::GameType;
+use crate::models::NetConfig;
+use crate::resources::GameTask;
+use crate::resources::GameTaskResource;
+use crate::resources::Message;
+use crate::resources::NetRecording;
+use crate::resources::NetResource;
+use crate::states::GameState;
+use crate::systems::net::ConnectionUpdateSystem;
+use crate::systems::net::MessageReceiveSystem;
+use crate::utils::TakeContent;
+use amethyst::core::transform::Transform;
+use amethyst::core::Time;
+use amethyst::ecs::Dispatcher;
+use amethyst::ecs::DispatcherBuilder;
+use amethyst::ecs::Entity;
+use amethyst::ecs::Join;
+use amethyst::prelude::*;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Simulated time of a single update
+const TICK: Duration = Duration::from_millis(10);
+
+/// How long to wait for a condition before giving up
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A headless server and clients talking to each other over 127.0.0.1
+pub struct Loopback {
+    pub server: LoopbackServer,
+    pub clients: Vec<LoopbackClient>,
+}
+
+/// Runs the same systems and game state as a dedicated server
+pub struct LoopbackServer {
+    world: World,
+    game_data: GameData<'static, 'static>,
+    state: GameState,
+    address: SocketAddr,
+}
+
+/// Runs the net systems only and collects game tasks they produce instead of handling them
+pub struct LoopbackClient {
+    world: World,
+    dispatcher: Dispatcher<'static, 'static>,
+    tasks: Vec<GameTask>,
+}
+
+impl Loopback {
+    pub fn new(clients_count: usize) -> amethyst::Result<Self> {
+        return Self::with_config(clients_count, &NetConfig::default());
+    }
+
+    /// Everyone uses the same config, thus the same network conditions if set
+    pub fn with_config(clients_count: usize, config: &NetConfig) -> amethyst::Result<Self> {
+        let server = LoopbackServer::new(config.clone())?;
+        let mut clients = Vec::with_capacity(clients_count);
+
+        for i in 0..clients_count {
+            let mut config = config.clone();
+
+            // Don't let everyone lose the same packets
+            if let Some(conditioner) = config.conditioner.as_mut() {
+                conditioner.seed = conditioner.seed.wrapping_add(i as u64 + 1);
+            }
+
+            clients.push(LoopbackClient::new(server.get_address(), config)?);
+        }
+
+        return Ok(Self { server, clients });
+    }
+
+    /// Updates the server and the clients until the condition is met. Returns `false` if it
+    /// hasn't been met in time
+    pub fn run_until<F: Fn(&Self) -> bool>(&mut self, condition: F) -> bool {
+        let start = Instant::now();
+
+        while start.elapsed() < TIMEOUT {
+            self.server.update();
+
+            for client in &mut self.clients {
+                client.update();
+            }
+
+            if condition(self) {
+                return true;
+            }
+
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        return false;
+    }
+}
+
+impl LoopbackServer {
+    pub fn new(mut config: NetConfig) -> amethyst::Result<Self> {
+        // Keep bans of a real server intact
+        config.ban_list = None;
+
+        let mut world = World::new();
+        let mut game_data = crate::build_headless_game_data()?.build(&mut world);
+        let net = NetResource::new_as_server(0, config).map_err(amethyst::Error::from_string)?;
+        let address = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), net.socket.local_addr()?.port());
+        let mut state = GameState::new_headless(GameType::Server(address.port()));
+
+        world.insert(net);
+        state.on_start(StateData::new(&mut world, &mut game_data));
+
+        return Ok(Self {
+            world,
+            game_data,
+            state,
+            address,
+        });
+    }
+
+    pub fn update(&mut self) {
+        advance_time(&self.world);
+        self.state
+            .update(&mut StateData::new(&mut self.world, &mut self.game_data));
+        self.game_data.update(&self.world);
+        self.world.maintain();
+    }
+
+    /// Runs the command as if the admin has typed it into the console
+    pub fn run_command(&mut self, line: &str) -> amethyst::Result<()> {
+        let command = ConsoleCommand::parse(line).map_err(amethyst::Error::from_string)?;
+
+        self.world
+            .write_resource::<GameTaskResource>()
+            .push(GameTask::ConsoleCommand(command));
+
+        return Ok(());
+    }
+
+    /// Hits the entity as if a projectile has done it
+    pub fn hit(&mut self, entity: Entity, force: f32) {
+        self.world
+            .write_resource::<GameTaskResource>()
+            .push(GameTask::ProjectileHit {
+                entity,
+                force_x: force,
+                force_y: 0.0,
+                shooter: None,
+            });
+    }
+
+    /// Teleports the entity
+    pub fn move_to(&mut self, entity: Entity, x: f32, y: f32) {
+        if let Some(transform) = self.world.write_storage::<Transform>().get_mut(entity) {
+            transform.set_translation_x(x);
+            transform.set_translation_y(y);
+        }
+    }
+
+    pub fn get_actors(&self) -> Vec<Entity> {
+        return (&self.world.entities(), &self.world.read_storage::<Actor>())
+            .join()
+            .map(|(e, _)| e)
+            .collect();
+    }
+
+    /// Entities of the connected clients
+    pub fn get_attached(&self) -> Vec<Entity> {
+        return self
+            .world
+            .read_resource::<NetResource>()
+            .connections
+            .values()
+            .filter_map(|c| c.attached_entity)
+            .collect();
+    }
+
+    pub fn count_actors(&self) -> usize {
+        return self.world.read_storage::<Actor>().join().count();
+    }
+
+    pub fn count_connections(&self) -> usize {
+        return self.world.read_resource::<NetResource>().connections.len();
+    }
+
+    pub const fn get_address(&self) -> SocketAddr {
+        return self.address;
+    }
+}
+
+impl LoopbackClient {
+    pub fn new(server_address: SocketAddr, config: NetConfig) -> amethyst::Result<Self> {
+        return Ok(Self::with_net(
+            NetResource::new_as_client(server_address, config)
+                .map_err(amethyst::Error::from_string)?,
+        ));
+    }
+
+    /// Joins from a new address presenting the token of a previous session
+    pub fn new_rejoining(
+        server_address: SocketAddr,
+        config: NetConfig,
+        session_token: u64,
+    ) -> amethyst::Result<Self> {
+        let mut net = NetResource::new_as_client(server_address, config)
+            .map_err(amethyst::Error::from_string)?;
+
+        net.rejoin(server_address, session_token);
+
+        return Ok(Self::with_net(net));
+    }
+
+    /// Plays the recorded session back without a server
+    pub fn new_replay(recording: NetRecording) -> amethyst::Result<Self> {
+        let server_address = recording
+            .get_server_address()
+            .ok_or_else(|| amethyst::Error::from_string("Nothing has been received"))?;
+
+        return Ok(Self::with_net(
+            NetResource::new_as_replay(server_address, recording, NetConfig::default())
+                .map_err(amethyst::Error::from_string)?,
+        ));
+    }
+
+    fn with_net(net: NetResource) -> Self {
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(MessageReceiveSystem, "message_receive", &[])
+            .with(
+                ConnectionUpdateSystem,
+                "connection_update",
+                &["message_receive"],
+            )
+            .build();
+
+        dispatcher.setup(&mut world);
+        world.insert(net);
+
+        return Self {
+            world,
+            dispatcher,
+            tasks: Vec::new(),
+        };
+    }
+
+    pub fn update(&mut self) {
+        advance_time(&self.world);
+        self.dispatcher.dispatch(&self.world);
+        self.world.maintain();
+
+        let tasks = self
+            .world
+            .write_resource::<GameTaskResource>()
+            .take_content();
+
+        self.tasks.extend(tasks);
+    }
+
+    pub fn leave(&mut self) {
+        self.world.write_resource::<NetResource>().leave("Testing");
+    }
+
+    pub fn say(&mut self, text: &str) {
+        self.world
+            .write_resource::<NetResource>()
+            .send_to_all(Message::ChatSay {
+                id: 0,
+                text: text.to_string(),
+            });
+    }
+
+    pub fn request_play(&mut self) {
+        self.world
+            .write_resource::<NetResource>()
+            .send_to_all(Message::PlayRequest { id: 0 });
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        return self.tasks.iter().any(|t| matches!(*t, GameTask::Start));
+    }
+
+    pub fn get_session_token(&self) -> Option<u64> {
+        return self
+            .world
+            .read_resource::<NetResource>()
+            .connections
+            .values()
+            .find_map(|c| c.session_token);
+    }
+
+    pub fn get_disconnect_reason(&self) -> Option<&str> {
+        return self.tasks.iter().find_map(|t| match *t {
+            GameTask::Disconnected(ref reason) => Some(reason.as_str()),
+            _ => None,
+        });
+    }
+
+    pub fn get_spawned(&self) -> Vec<Entity> {
+        return self
+            .tasks
+            .iter()
+            .filter_map(|t| match *t {
+                GameTask::ActorSpawn { entity, .. } => Some(entity),
+                _ => None,
+            })
+            .collect();
+    }
+
+    pub fn get_granted(&self) -> Option<Entity> {
+        return self.tasks.iter().find_map(|t| match *t {
+            GameTask::ActorGrant { entity } => Some(entity),
+            _ => None,
+        });
+    }
+
+    /// Names of players in the latest roster told by the server
+    pub fn get_roster_names(&self) -> Vec<String> {
+        return self
+            .tasks
+            .iter()
+            .rev()
+            .find_map(|t| match *t {
+                GameTask::Roster(ref players) => Some(players),
+                _ => None,
+            })
+            .map_or_else(Vec::new, |players| {
+                players.iter().map(|p| p.name.clone()).collect()
+            });
+    }
+
+    /// Senders and texts of chat lines relayed by the server
+    pub fn get_chat(&self) -> Vec<(String, String)> {
+        return self
+            .tasks
+            .iter()
+            .filter_map(|t| match *t {
+                GameTask::Chat {
+                    ref sender,
+                    ref text,
+                } => Some((sender.clone(), text.clone())),
+                _ => None,
+            })
+            .collect();
+    }
+
+    /// The latest health of the actor told by the server
+    pub fn get_health(&self, entity: Entity) -> Option<f32> {
+        return self
+            .tasks
+            .iter()
+            .rev()
+            .filter_map(|t| match *t {
+                GameTask::ActorSpawn {
+                    entity: e, health, ..
+                }
+                | GameTask::ActorHealth { entity: e, health } => Some((e, health)),
+                _ => None,
+            })
+            .find(|(e, _)| *e == entity)
+            .map(|(_, health)| health);
+    }
+
+    pub fn get_deleted(&self) -> Vec<Entity> {
+        return self
+            .tasks
+            .iter()
+            .filter_map(|t| match *t {
+                GameTask::EntityDelete(entity) => Some(entity),
+                _ => None,
+            })
+            .collect();
+    }
+}
+
+fn advance_time(world: &World) {
+    world.write_resource::<Time>().set_delta_time(TICK);
+}
@@ -0,0 +1,683 @@
+use crate::data::BUILD_HASH;
+use crate::data::VIEW_DISTANCE;
+use crate::data::WORLD_SIZE_HALF;
+use crate::models::NetConditionerConfig;
+use crate::models::NetConfig;
+use crate::resources::LanDiscovery;
+use crate::resources::Message;
+use crate::resources::NetRecording;
+use crate::resources::Packet;
+use crate::resources::PacketHeader;
+use crate::resources::PACKET_SIZE_MAX;
+use crate::resources::PROTOCOL_VERSION;
+use crate::tests::loopback::Loopback;
+use crate::tests::loopback::LoopbackClient;
+use std::cell::RefCell;
+use std::net::UdpSocket;
+use std::time::Duration;
+
+#[test]
+fn test_join() -> amethyst::Result<()> {
+    let mut loopback = Loopback::new(1)?;
+    let actors = loopback.server.count_actors() + 1;
+
+    assert!(
+        loopback.run_until(|l| l.clients.iter().all(|c| c.get_granted().is_some())),
+        "Granted an actor",
+    );
+
+    for client in &loopback.clients {
+        let spawned = client.get_spawned();
+        assert!(client.is_accepted());
+        assert_eq!(actors, spawned.len(), "Spawned every actor");
+        assert!(
+            client.get_granted().map_or(false, |e| spawned.contains(&e)),
+            "Granted a spawned actor",
+        );
+    }
+
+    return Ok(());
+}
+
+#[test]
+fn test_join_several() -> amethyst::Result<()> {
+    let mut loopback = Loopback::new(3)?;
+    let actors = loopback.server.count_actors() + loopback.clients.len();
+
+    assert!(
+        loopback.run_until(|l| l.clients.iter().all(|c| c.get_spawned().len() == actors)),
+        "Clients see each other",
+    );
+
+    return Ok(());
+}
+
+#[test]
+fn test_join_over_bad_network() -> amethyst::Result<()> {
+    let config = NetConfig {
+        conditioner: Some(NetConditionerConfig {
+            loss: 0.2,
+            latency: Duration::from_millis(20),
+            jitter: Duration::from_millis(20),
+            duplication: 0.1,
+            reordering: 0.2,
+            seed: 1,
+        }),
+        ..NetConfig::default()
+    };
+
+    let mut loopback = Loopback::with_config(2, &config)?;
+    let actors = loopback.server.count_actors() + loopback.clients.len();
+
+    assert!(
+        loopback.run_until(|l| l.clients.iter().all(|c| c.get_spawned().len() == actors)),
+        "Lost messages are resent, duplicated and reordered ones are handled once and in order",
+    );
+
+    for client in &loopback.clients {
+        assert!(client.get_granted().is_some());
+    }
+
+    return Ok(());
+}
+
+#[test]
+fn test_join_full() -> amethyst::Result<()> {
+    let config = NetConfig {
+        players_max: 1,
+        ..NetConfig::default()
+    };
+
+    let mut loopback = Loopback::with_config(2, &config)?;
+
+    assert!(
+        loopback.run_until(|l| l.clients.iter().any(LoopbackClient::is_accepted)
+            && l.clients.iter().any(|c| c
+                .get_disconnect_reason()
+                .map_or(false, |r| r.contains("full")))),
+        "One client has joined while the other one has been rejected",
+    );
+
+    assert!(loopback.run_until(|l| l.server.count_connections() == 1));
+
+    return Ok(());
+}
+
+#[test]
+fn test_join_ignore_strangers() -> amethyst::Result<()> {
+    let mut loopback = Loopback::new(0)?;
+    let socket = UdpSocket::bind("127.0.0.1:0")?;
+    let mut packet = Packet::new(PacketHeader {
+        ack: 0,
+        ack_bits: 0,
+    });
+
+    packet.push(&Message::SnapshotAck { tick: 1 }.encode());
+    socket.send_to(b"garbage", loopback.server.get_address())?;
+    socket.send_to(packet.as_bytes(), loopback.server.get_address())?;
+
+    let client = LoopbackClient::new(loopback.server.get_address(), NetConfig::default())?;
+    loopback.clients.push(client);
+
+    assert!(loopback.run_until(|l| l.clients.iter().all(LoopbackClient::is_accepted)));
+    assert_eq!(
+        1,
+        loopback.server.count_connections(),
+        "No connection without joining",
+    );
+
+    return Ok(());
+}
+
+#[test]
+fn test_join_other_version() -> amethyst::Result<()> {
+    let mut loopback = Loopback::new(0)?;
+    let socket = UdpSocket::bind("127.0.0.1:0")?;
+    let mut packet = Packet::new(PacketHeader {
+        ack: 0,
+        ack_bits: 0,
+    });
+
+    // Any other version joins with the same message
+    packet.push(
+        &Message::Join {
+            id: 0,
+            protocol_version: PROTOCOL_VERSION.wrapping_add(1),
+            build: BUILD_HASH.to_string(),
+        }
+        .encode(),
+    );
+
+    socket.set_nonblocking(true)?;
+    socket.send_to(packet.as_bytes(), loopback.server.get_address())?;
+
+    let reason = RefCell::new(None);
+
+    assert!(
+        loopback.run_until(|_| {
+            let mut buffer = [0; PACKET_SIZE_MAX];
+
+            if let Ok((size, _)) = socket.recv_from(&mut buffer) {
+                let messages = buffer
+                    .get(..size)
+                    .and_then(|p| Packet::decode(p).ok())
+                    .map_or_else(Vec::new, |(_, messages)| messages);
+
+                for message in messages {
+                    if let Message::JoinReject { reason: text, .. } = message {
+                        *reason.borrow_mut() = Some(text);
+                    }
+                }
+            }
+
+            reason.borrow().is_some()
+        }),
+        "Rejected",
+    );
+
+    assert!(reason
+        .into_inner()
+        .map_or(false, |r| r.contains("protocol version")));
+
+    return Ok(());
+}
+
+#[test]
+fn test_leave() -> amethyst::Result<()> {
+    let mut loopback = Loopback::new(2)?;
+
+    assert!(loopback.run_until(|l| l.clients.iter().all(|c| c.get_granted().is_some())));
+
+    if let Some(client) = loopback.clients.first_mut() {
+        client.leave();
+    }
+
+    assert!(
+        loopback.run_until(|l| l
+            .clients
+            .last()
+            .map_or(false, |c| c.get_deleted().len() == 1)),
+        "The remaining client has deleted the left one",
+    );
+
+    return Ok(());
+}
+
+#[test]
+fn test_rejoin() -> amethyst::Result<()> {
+    let mut loopback = Loopback::new(1)?;
+
+    assert!(loopback.run_until(|l| l.clients.iter().all(|c| c.get_granted().is_some())));
+
+    let actors = loopback.server.count_actors();
+    let session_token = loopback
+        .clients
+        .pop()
+        .and_then(|c| c.get_session_token())
+        .ok_or_else(|| amethyst::Error::from_string("No session token"))?;
+
+    // The server hasn't noticed the old address is gone yet
+    let client = LoopbackClient::new_rejoining(
+        loopback.server.get_address(),
+        NetConfig::default(),
+        session_token,
+    )?;
+
+    loopback.clients.push(client);
+
+    assert!(
+        loopback.run_until(|l| l.clients.iter().all(|c| c.get_granted().is_some())
+            && l.server.count_connections() == 1),
+        "Granted the actor again and dropped the old connection",
+    );
+
+    assert_eq!(actors, loopback.server.count_actors(), "No new actor");
+
+    return Ok(());
+}
+
+#[test]
+fn test_rejoin_after_timeout() -> amethyst::Result<()> {
+    let config = NetConfig {
+        timeout: Duration::from_millis(300),
+        heartbeat_interval: Duration::from_millis(50),
+        ..NetConfig::default()
+    };
+
+    let mut loopback = Loopback::with_config(1, &config)?;
+
+    assert!(loopback.run_until(|l| l.clients.iter().all(|c| c.get_granted().is_some())));
+
+    let actors = loopback.server.count_actors();
+    let session_token = loopback
+        .clients
+        .pop()
+        .and_then(|c| c.get_session_token())
+        .ok_or_else(|| amethyst::Error::from_string("No session token"))?;
+
+    assert!(
+        loopback.run_until(|l| l.server.count_connections() == 0),
+        "Timed out",
+    );
+
+    assert_eq!(actors, loopback.server.count_actors(), "The actor is kept");
+
+    let client =
+        LoopbackClient::new_rejoining(loopback.server.get_address(), config, session_token)?;
+
+    loopback.clients.push(client);
+
+    assert!(loopback.run_until(|l| l
+        .clients
+        .iter()
+        .all(|c| c.get_granted().is_some() && c.get_roster_names().len() == 1)));
+
+    assert_eq!(actors, loopback.server.count_actors(), "No new actor");
+
+    return Ok(());
+}
+
+#[test]
+fn test_rejoin_after_deletion() -> amethyst::Result<()> {
+    let config = NetConfig {
+        timeout: Duration::from_millis(300),
+        heartbeat_interval: Duration::from_millis(50),
+        ..NetConfig::default()
+    };
+
+    let mut loopback = Loopback::with_config(2, &config)?;
+
+    assert!(loopback.run_until(|l| l.clients.iter().all(|c| c.get_granted().is_some())));
+
+    let actors = loopback.server.count_actors();
+
+    assert!(
+        loopback.run_until(|l| l.clients.iter().all(|c| c.get_spawned().len() == actors)),
+        "Everyone has seen every actor",
+    );
+
+    // Stop updating the client so it misses what happens meanwhile
+    let client = loopback
+        .clients
+        .pop()
+        .ok_or_else(|| amethyst::Error::from_string("No client"))?;
+
+    let spawned = client.get_spawned();
+
+    assert!(
+        loopback.run_until(|l| l.server.count_connections() == 1),
+        "Timed out",
+    );
+
+    for other in &mut loopback.clients {
+        other.leave();
+    }
+
+    assert!(
+        loopback.run_until(|l| l.server.count_actors() == actors - 1),
+        "The left actor is deleted while the client is away",
+    );
+
+    loopback.clients.push(client);
+
+    assert!(
+        loopback.run_until(|l| l.clients.last().map_or(false, |c| c.get_spawned().len()
+            == spawned.len() + l.server.count_actors())),
+        "Spawned the remaining actors again after rejoining",
+    );
+
+    let deleted = loopback
+        .clients
+        .last()
+        .map_or_else(Vec::new, LoopbackClient::get_deleted);
+
+    assert!(
+        spawned.iter().all(|e| deleted.contains(e)),
+        "Nothing is kept from before the rejoin",
+    );
+
+    return Ok(());
+}
+
+#[test]
+fn test_damage_and_death() -> amethyst::Result<()> {
+    let mut loopback = Loopback::new(1)?;
+
+    assert!(loopback.run_until(|l| l.clients.iter().all(|c| c.get_granted().is_some())));
+
+    let actor = loopback
+        .server
+        .get_attached()
+        .first()
+        .copied()
+        .ok_or_else(|| amethyst::Error::from_string("No actor"))?;
+
+    loopback.server.hit(actor, 0.1);
+
+    assert!(
+        loopback.run_until(|l| l.clients.iter().all(|c| c
+            .get_granted()
+            .and_then(|e| c.get_health(e))
+            .map_or(false, |h| h > 0.0 && h < 1.0))),
+        "Damaged",
+    );
+
+    loopback.server.hit(actor, 1000.0);
+
+    assert!(
+        loopback.run_until(|l| l.clients.iter().all(|c| c
+            .get_granted()
+            .and_then(|e| c.get_health(e))
+            .map_or(false, |h| h <= 0.0))),
+        "Died",
+    );
+
+    return Ok(());
+}
+
+#[test]
+fn test_kick_and_ban() -> amethyst::Result<()> {
+    let mut loopback = Loopback::new(1)?;
+
+    assert!(loopback.run_until(|l| l.clients.iter().all(|c| c.get_granted().is_some())));
+
+    let actors = loopback.server.count_actors();
+    loopback.server.run_command("kick 0")?;
+
+    assert!(loopback.run_until(|l| l.clients.iter().all(|c| c
+        .get_disconnect_reason()
+        .map_or(false, |r| r.starts_with("Kicked")))));
+
+    assert!(
+        loopback.run_until(|l| l.server.count_actors() == actors - 1),
+        "Deleted the actor of the kicked player",
+    );
+
+    loopback.server.run_command("ban 127.0.0.1")?;
+
+    let client = LoopbackClient::new(loopback.server.get_address(), NetConfig::default())?;
+    loopback.clients = vec![client];
+
+    assert!(loopback.run_until(|l| l.clients.iter().all(|c| c
+        .get_disconnect_reason()
+        .map_or(false, |r| r.contains("banned")))));
+
+    return Ok(());
+}
+
+#[test]
+fn test_restart() -> amethyst::Result<()> {
+    let mut loopback = Loopback::new(1)?;
+
+    assert!(loopback.run_until(|l| l.clients.iter().all(|c| c.get_granted().is_some())));
+
+    let actors = loopback.server.count_actors();
+    let granted = loopback
+        .clients
+        .first()
+        .and_then(LoopbackClient::get_granted);
+    loopback.server.run_command("restart")?;
+
+    assert!(
+        loopback.run_until(|l| l
+            .clients
+            .iter()
+            .all(|c| granted.map_or(false, |g| c.get_deleted().contains(&g)))),
+        "The old actor is gone",
+    );
+
+    assert!(loopback.run_until(|l| l.server.count_actors() == actors));
+    assert_eq!(
+        vec!["Player".to_string()],
+        loopback
+            .clients
+            .first()
+            .map_or_else(Vec::new, LoopbackClient::get_roster_names),
+        "Still in the roster",
+    );
+
+    return Ok(());
+}
+
+#[test]
+fn test_roster() -> amethyst::Result<()> {
+    let mut loopback = Loopback::new(2)?;
+
+    assert!(
+        loopback.run_until(|l| l.clients.iter().all(|c| c.get_roster_names().len() == 2)),
+        "Everyone knows both players",
+    );
+
+    for client in &loopback.clients {
+        assert_eq!(
+            vec!["Player".to_string(), "Player 2".to_string()],
+            client.get_roster_names(),
+            "Names are unique",
+        );
+    }
+
+    if let Some(client) = loopback.clients.first_mut() {
+        client.leave();
+    }
+
+    assert!(
+        loopback.run_until(|l| l
+            .clients
+            .last()
+            .map_or(false, |c| c.get_roster_names().len() == 1)),
+        "The left player is gone",
+    );
+
+    return Ok(());
+}
+
+#[test]
+fn test_spectate() -> amethyst::Result<()> {
+    let mut loopback = Loopback::new(0)?;
+    let actors = loopback.server.count_actors();
+    let config = NetConfig {
+        spectate: true,
+        ..NetConfig::default()
+    };
+
+    let client = LoopbackClient::new(loopback.server.get_address(), config)?;
+    loopback.clients.push(client);
+
+    assert!(
+        loopback.run_until(|l| l
+            .clients
+            .iter()
+            .all(|c| c.get_spawned().len() == actors && c.get_roster_names().len() == 1)),
+        "Spawned every actor and listed the spectator",
+    );
+
+    assert_eq!(
+        actors,
+        loopback.server.count_actors(),
+        "No actor for the spectator"
+    );
+
+    for client in &mut loopback.clients {
+        assert!(client.get_granted().is_none());
+        client.request_play();
+    }
+
+    assert!(
+        loopback.run_until(|l| l.clients.iter().all(|c| c.get_granted().is_some())),
+        "Switched into play",
+    );
+
+    assert_eq!(actors + 1, loopback.server.count_actors());
+
+    return Ok(());
+}
+
+#[test]
+fn test_relevance() -> amethyst::Result<()> {
+    let mut loopback = Loopback::new(1)?;
+
+    assert!(loopback.run_until(|l| l.clients.iter().all(|c| c.get_granted().is_some())));
+
+    let attached = loopback.server.get_attached();
+    let zombie = loopback
+        .server
+        .get_actors()
+        .into_iter()
+        .find(|a| !attached.contains(a))
+        .ok_or_else(|| amethyst::Error::from_string("No zombie"))?;
+
+    let config = NetConfig {
+        spectate: true,
+        ..NetConfig::default()
+    };
+
+    let spectator = LoopbackClient::new(loopback.server.get_address(), config)?;
+    loopback.clients.push(spectator);
+
+    let actors = loopback.server.count_actors();
+
+    assert!(
+        loopback.run_until(|l| l.clients.iter().all(|c| c.get_spawned().len() == actors)),
+        "Everyone has seen every actor",
+    );
+
+    // The player stays at the center
+    loopback
+        .server
+        .move_to(zombie, WORLD_SIZE_HALF, WORLD_SIZE_HALF);
+
+    assert!(
+        loopback.run_until(|l| l
+            .clients
+            .first()
+            .map_or(false, |c| c.get_deleted().len() == 1)),
+        "Despawned for the player",
+    );
+
+    loopback.server.move_to(zombie, 0.0, VIEW_DISTANCE / 2.0);
+
+    assert!(
+        loopback.run_until(|l| l
+            .clients
+            .first()
+            .map_or(false, |c| c.get_spawned().len() == actors + 1)),
+        "Spawned again for the player",
+    );
+
+    for spectator in loopback.clients.iter().skip(1) {
+        assert!(
+            spectator.get_deleted().is_empty(),
+            "Spectators see everything"
+        );
+        assert_eq!(actors, spectator.get_spawned().len());
+    }
+
+    return Ok(());
+}
+
+#[test]
+fn test_chat() -> amethyst::Result<()> {
+    let mut loopback = Loopback::new(0)?;
+
+    for name in &["Alice", "Bob"] {
+        let config = NetConfig {
+            player_name: (*name).to_string(),
+            ..NetConfig::default()
+        };
+
+        let client = LoopbackClient::new(loopback.server.get_address(), config)?;
+        loopback.clients.push(client);
+    }
+
+    assert!(loopback.run_until(|l| l.clients.iter().all(|c| c.get_roster_names().len() == 2)));
+
+    if let Some(client) = loopback.clients.first_mut() {
+        client.say(" Hello\n");
+    }
+
+    assert!(
+        loopback.run_until(|l| l.clients.iter().all(|c| !c.get_chat().is_empty())),
+        "Everyone has got the line",
+    );
+
+    for client in &loopback.clients {
+        assert_eq!(
+            vec![("Alice".to_string(), "Hello".to_string())],
+            client.get_chat(),
+            "Relayed with the name of the sender",
+        );
+    }
+
+    return Ok(());
+}
+
+#[test]
+fn test_replay() -> amethyst::Result<()> {
+    let path = std::env::temp_dir().join(format!("shooter_replay_{}.rec", std::process::id()));
+    let config = NetConfig {
+        record: Some(path.clone()),
+        ..NetConfig::default()
+    };
+
+    let mut loopback = Loopback::new(0)?;
+    let actors = loopback.server.count_actors() + 1;
+    let client = LoopbackClient::new(loopback.server.get_address(), config)?;
+    loopback.clients.push(client);
+
+    assert!(loopback.run_until(|l| l.clients.iter().all(|c| c.get_granted().is_some())));
+
+    loopback.clients.clear();
+    let recording = NetRecording::load(&path).map_err(amethyst::Error::from_string)?;
+    std::fs::remove_file(&path)?;
+    let replay = LoopbackClient::new_replay(recording)?;
+    loopback.clients.push(replay);
+
+    assert!(
+        loopback.run_until(|l| l
+            .clients
+            .iter()
+            .all(|c| c.get_disconnect_reason().is_some())),
+        "Played to the end",
+    );
+
+    for client in &loopback.clients {
+        assert!(client.is_accepted());
+        assert_eq!(actors, client.get_spawned().len(), "Same actors");
+        assert!(client.get_granted().is_some());
+    }
+
+    return Ok(());
+}
+
+#[test]
+fn test_discovery() -> amethyst::Result<()> {
+    // Any free port, so servers of other tests don't answer
+    let discovery_port = UdpSocket::bind("127.0.0.1:0")?.local_addr()?.port();
+    let config = NetConfig {
+        server_name: "Test".to_string(),
+        discovery_port,
+        ..NetConfig::default()
+    };
+
+    let mut loopback = Loopback::with_config(1, &config)?;
+    let port = loopback.server.get_address().port();
+
+    assert!(loopback.run_until(|l| l.clients.iter().all(|c| c.get_granted().is_some())));
+
+    let discovery =
+        RefCell::new(LanDiscovery::new(discovery_port).map_err(amethyst::Error::from_string)?);
+
+    assert!(
+        loopback.run_until(|_| discovery.borrow_mut().update()),
+        "Found the server",
+    );
+
+    let discovery = discovery.into_inner();
+    let servers = discovery.get_servers();
+    assert_eq!(1, servers.len(), "Listed once");
+    assert!(servers
+        .iter()
+        .all(|s| s.address.port() == port && s.info.name == "Test" && s.info.players == 1));
+
+    return Ok(());
+}
@@ -1,6 +1,8 @@
 mod duration_ext;
 pub mod math;
 mod position;
+mod rtt_estimator;
+mod sequence;
 mod timer;
 pub mod ui;
 pub mod world_decorations;
@@ -8,6 +10,8 @@ mod world_ext_custom;
 
 pub use self::duration_ext::*;
 pub use self::position::*;
+pub use self::rtt_estimator::*;
+pub use self::sequence::*;
 pub use self::timer::*;
 pub use self::world_ext_custom::*;
 use amethyst::core::HiddenPropagate;
@@ -31,6 +35,20 @@ pub fn set_entity_visibility(world: &World, entity: Entity, is_visibility: bool)
     }
 }
 
+/// Drops control characters and surrounding spaces and limits the length
+pub fn sanitize_text(text: &str, length_max: usize) -> String {
+    return text
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect::<String>()
+        .trim()
+        .chars()
+        .take(length_max)
+        .collect::<String>()
+        .trim_end()
+        .to_string();
+}
+
 pub trait TakeContent<T> {
     fn take_content(&mut self) -> T;
 }
@@ -0,0 +1,99 @@
+use crate::utils::DurationExt;
+use std::time::Duration;
+
+const TIMEOUT_INITIAL: Duration = Duration::from_millis(400);
+const TIMEOUT_MIN: Duration = Duration::from_millis(50);
+const TIMEOUT_MAX: Duration = Duration::from_secs(4);
+
+/// Estimates round-trip time and retransmission timeout the way TCP does, see RFC 6298
+pub struct RttEstimator {
+    smoothed: Option<Duration>,
+    variation: Duration,
+}
+
+impl RttEstimator {
+    pub const fn new() -> Self {
+        return Self {
+            smoothed: None,
+            variation: Duration::from_millis(0),
+        };
+    }
+
+    pub fn add_sample(&mut self, rtt: Duration) {
+        if let Some(smoothed) = self.smoothed {
+            let difference = smoothed.sub_safely(rtt) + rtt.sub_safely(smoothed);
+            self.variation = (self.variation * 3 + difference) / 4;
+            self.smoothed = Some((smoothed * 7 + rtt) / 8);
+        } else {
+            self.smoothed = Some(rtt);
+            self.variation = rtt / 2;
+        }
+    }
+
+    /// Smoothed round-trip time, unknown until the first sample
+    pub const fn get_rtt(&self) -> Option<Duration> {
+        return self.smoothed;
+    }
+
+    /// How long to wait for acknowledgement of a message which has been resent the given number
+    /// of times, the timeout doubles on every resend
+    pub fn get_timeout(&self, resends: u32) -> Duration {
+        let mut timeout = match self.smoothed {
+            Some(smoothed) => (smoothed + self.variation * 4).max(TIMEOUT_MIN),
+            None => TIMEOUT_INITIAL,
+        };
+
+        for _ in 0..resends {
+            if timeout >= TIMEOUT_MAX {
+                break;
+            }
+
+            timeout *= 2;
+        }
+
+        return timeout.min(TIMEOUT_MAX);
+    }
+}
+
+impl Default for RttEstimator {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        return Duration::from_millis(millis);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut rtt = RttEstimator::new();
+        assert_eq!(None, rtt.get_rtt());
+        assert_eq!(TIMEOUT_INITIAL, rtt.get_timeout(0), "Initial");
+
+        rtt.add_sample(ms(100));
+        assert_eq!(Some(ms(100)), rtt.get_rtt(), "First sample");
+        assert_eq!(ms(300), rtt.get_timeout(0), "First sample");
+
+        for _ in 0..200 {
+            rtt.add_sample(ms(20));
+        }
+
+        assert_eq!(Some(ms(20)), rtt.get_rtt(), "Stable link");
+        assert_eq!(TIMEOUT_MIN, rtt.get_timeout(0), "Stable link");
+    }
+
+    #[test]
+    fn test_timeout_backoff() {
+        let mut rtt = RttEstimator::new();
+        rtt.add_sample(ms(100));
+        assert_eq!(ms(600), rtt.get_timeout(1));
+        assert_eq!(ms(1200), rtt.get_timeout(2));
+        assert_eq!(TIMEOUT_MAX, rtt.get_timeout(5), "Limited");
+        assert_eq!(TIMEOUT_MAX, rtt.get_timeout(u32::MAX), "Limited");
+    }
+}
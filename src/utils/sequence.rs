@@ -0,0 +1,46 @@
+use std::cmp::Ordering;
+
+/// Comparison of sequence numbers which may wrap around, see RFC 1982
+pub trait SequenceExt {
+    fn cmp_sequence(self, other: Self) -> Ordering;
+}
+
+impl SequenceExt for u16 {
+    fn cmp_sequence(self, other: Self) -> Ordering {
+        let distance = self.wrapping_sub(other);
+
+        if distance == 0 {
+            return Ordering::Equal;
+        } else if distance < 0x8000 {
+            return Ordering::Greater;
+        } else {
+            return Ordering::Less;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cmp_sequence() {
+        assert_eq!(Ordering::Equal, 0.cmp_sequence(0));
+        assert_eq!(Ordering::Equal, u16::MAX.cmp_sequence(u16::MAX));
+
+        // Without wrapping
+        assert_eq!(Ordering::Greater, 2.cmp_sequence(1));
+        assert_eq!(Ordering::Less, 1.cmp_sequence(2));
+        assert_eq!(Ordering::Greater, 30_000.cmp_sequence(0));
+
+        // Across the wrap boundary
+        assert_eq!(Ordering::Greater, 0.cmp_sequence(u16::MAX));
+        assert_eq!(Ordering::Less, u16::MAX.cmp_sequence(0));
+        assert_eq!(Ordering::Greater, 10.cmp_sequence(65_530));
+        assert_eq!(Ordering::Less, 65_530.cmp_sequence(10));
+
+        // More than a half of the range apart is considered as wrapped
+        assert_eq!(Ordering::Less, 40_000.cmp_sequence(0));
+        assert_eq!(Ordering::Greater, 0.cmp_sequence(40_000));
+    }
+}
@@ -9,6 +9,7 @@ use crate::components::Player;
 use crate::components::Projectile;
 use crate::components::ProjectileConfig;
 use crate::components::RigidBody;
+use crate::components::Spectator;
 use crate::components::Terrain;
 use crate::components::Weapon;
 use crate::components::WeaponConfig;
@@ -17,8 +18,6 @@ use crate::data::LAYER_ACTOR_PLAYER;
 use crate::data::LAYER_CAMERA;
 use crate::data::LAYER_TERRAIN;
 use crate::models::GameType;
-use crate::resources::Message;
-use crate::resources::NetResource;
 use crate::resources::Sprite;
 use crate::resources::SpriteResource;
 use crate::resources::State;
@@ -40,6 +39,7 @@ use amethyst::renderer::SpriteRender;
 use amethyst::renderer::Transparent;
 use amethyst::tiles::MortonEncoder;
 use amethyst::tiles::TileMap;
+use std::time::Duration;
 
 pub trait WorldExtCustom {
     fn add<T: Component>(&self, entity: Entity, component: T);
@@ -66,6 +66,8 @@ pub trait WorldExtCustom {
 
     fn create_camera(&mut self, target: Entity) -> Entity;
 
+    fn create_spectator(&mut self, root: Entity) -> Entity;
+
     fn create_projectile(
         &mut self,
         root: Entity,
@@ -73,6 +75,7 @@ pub trait WorldExtCustom {
         velocity: f32,
         acceleration_factor: f32,
         shooter: Option<Entity>,
+        rewind: Duration,
     ) -> Entity;
 
     fn create_terrain(&mut self, root: Entity) -> Entity;
@@ -116,7 +119,6 @@ impl WorldExtCustom for World {
             .build();
     }
 
-    // TODO: Send message here
     fn create_actor(
         &self,
         root: Entity,
@@ -126,16 +128,6 @@ impl WorldExtCustom for World {
         is_ghost: bool,
         game_type: &GameType,
     ) {
-        if game_type.is_server() {
-            self.write_resource::<NetResource>()
-                .send_to_all(Message::ActorSpawn {
-                    id: 0,
-                    entity_id: entity.id(),
-                    actor_type: actor_type.serialized,
-                    position,
-                });
-        }
-
         let mut transform = Transform::default();
         transform.set_translation_xyz(position.x, position.y, LAYER_ACTOR);
         transform.set_rotation_2d(position.direction);
@@ -143,6 +135,8 @@ impl WorldExtCustom for World {
         self.add(entity, transform);
         self.add(entity, Parent { entity: root });
         self.add(entity, Actor::new(actor_type));
+        // Clients mirror the health told by the server
+        self.add(entity, Health::new(actor_type.resistance));
         self.add(
             entity,
             Weapon::new(WeaponConfig {
@@ -157,7 +151,6 @@ impl WorldExtCustom for World {
         match *game_type {
             GameType::Server(..) => {
                 self.add(entity, Own);
-                self.add(entity, Health::new(actor_type.resistance));
             }
             GameType::Client(..) => {
                 let now = self.read_resource::<Time>().absolute_time();
@@ -165,9 +158,10 @@ impl WorldExtCustom for World {
             }
         }
 
+        // Sprites are absent while running headless
         if let Some(renderer) = self
-            .read_resource::<SpriteResource>()
-            .get(actor_type.sprite)
+            .try_fetch::<SpriteResource>()
+            .and_then(|s| s.get(actor_type.sprite))
             .map(|s| SpriteRender::new(s, 0))
         {
             self.add(entity, renderer);
@@ -199,6 +193,22 @@ impl WorldExtCustom for World {
             .build();
     }
 
+    fn create_spectator(&mut self, root: Entity) -> Entity {
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 0.0, LAYER_ACTOR_PLAYER);
+
+        let spectator = self
+            .create_entity()
+            .with(Parent { entity: root })
+            .with(transform)
+            .with(Spectator::new())
+            .build();
+
+        self.create_camera(spectator);
+
+        return spectator;
+    }
+
     fn create_projectile(
         &mut self,
         root: Entity,
@@ -206,6 +216,7 @@ impl WorldExtCustom for World {
         velocity: f32,
         acceleration_factor: f32,
         shooter: Option<Entity>,
+        rewind: Duration,
     ) -> Entity {
         let (sin, cos) = (-position.direction).sin_cos();
         let projectile = Projectile::new(
@@ -216,6 +227,7 @@ impl WorldExtCustom for World {
             Vector2::new(position.x, position.y),
             Vector2::new(velocity * sin, velocity * cos),
             shooter,
+            rewind,
         );
 
         return self